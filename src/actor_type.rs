@@ -0,0 +1,124 @@
+use crate::model::*;
+use serde::Deserialize;
+use std::collections;
+
+/// Rewrites actor ids starting with `prefix` into `namespace` followed by the rest of the id, e.g. `GENE:1545` into an NCBIGene IRI.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct IdRewrite {
+    pub prefix: String,
+    pub namespace: String,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum LabelStrategy {
+    /// The actor's text.
+    Text,
+    /// The actor's text, falling back to its `seqid` when CTD gives none.
+    TextOrSeqId,
+}
+
+/// How actors of one CTD `type` attribute are converted.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct ActorType {
+    /// Tried in order; an id no rule matches is used as is.
+    pub id_rewrites: Vec<IdRewrite>,
+    /// Class every actor individual of this type is asserted to, like `CHEMICAL_ENTITY` or `GENE_ENTITY`.
+    pub node_class: String,
+    pub label: LabelStrategy,
+    /// Whether ids are first looked up in the MESH to CHEBI mapping table.
+    #[serde(default)]
+    pub chebi_mapping: bool,
+    /// Biolink category of the KGX node.
+    #[serde(default = "default_kgx_category")]
+    pub kgx_category: String,
+}
+
+fn default_kgx_category() -> String {
+    String::from("biolink:NamedThing")
+}
+
+impl ActorType {
+    pub fn class_iri(&self, actor_id: &str) -> String {
+        for rewrite in self.id_rewrites.iter() {
+            if let Some(local) = actor_id.strip_prefix(rewrite.prefix.as_str()) {
+                return format!("{}{}", rewrite.namespace, local);
+            }
+        }
+        debug!("no id rewrite for: {:?}", actor_id);
+        actor_id.to_string()
+    }
+
+    pub fn label(&self, actor: &Actor) -> String {
+        match (self.label, &actor.text, &actor.seq_id) {
+            (_, Some(text), _) => text.clone(),
+            (LabelStrategy::TextOrSeqId, None, Some(seq_id)) => seq_id.clone(),
+            (LabelStrategy::Text, None, _) => {
+                warn!("text is empty - actor: {:?}", actor);
+                String::from("")
+            }
+            (LabelStrategy::TextOrSeqId, None, None) => {
+                warn!("seq_id is empty - actor: {:?}", actor);
+                String::from("")
+            }
+        }
+    }
+}
+
+/// The actor types the converter knows, keyed by the CTD `type` attribute; `ixn` is structural and never registered.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ActorTypeRegistry {
+    types: collections::HashMap<String, ActorType>,
+}
+
+impl Default for ActorTypeRegistry {
+    fn default() -> ActorTypeRegistry {
+        let mut registry = ActorTypeRegistry { types: collections::HashMap::new() };
+        registry.register(
+            "chemical",
+            ActorType {
+                id_rewrites: vec![IdRewrite { prefix: String::from("MESH:"), namespace: String::from(crate::MESH) }],
+                node_class: crate::CHEMICAL_ENTITY.clone(),
+                label: LabelStrategy::Text,
+                chebi_mapping: true,
+                kgx_category: String::from("biolink:ChemicalEntity"),
+            },
+        );
+        registry.register(
+            "gene",
+            ActorType {
+                id_rewrites: vec![IdRewrite { prefix: String::from("GENE:"), namespace: String::from(crate::NCBIGENE) }],
+                node_class: crate::GENE_ENTITY.clone(),
+                label: LabelStrategy::TextOrSeqId,
+                chebi_mapping: false,
+                kgx_category: String::from("biolink:Gene"),
+            },
+        );
+        registry
+    }
+}
+
+impl ActorTypeRegistry {
+    /// Adds a type, replacing any existing one of the same name.
+    pub fn register(&mut self, name: &str, actor_type: ActorType) {
+        self.types.insert(name.to_string(), actor_type);
+    }
+
+    pub fn get(&self, name: &str) -> Option<&ActorType> {
+        self.types.get(name)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&String, &ActorType)> {
+        self.types.iter()
+    }
+
+    /// Types of atomic actors anywhere in the interaction that are not registered.
+    pub fn unknown_types<'a>(&self, ixn: &'a IXN) -> Vec<&'a str> {
+        let mut unknown = ixn.actors.iter().flat_map(|a| a.flat()).map(|a| a.actor_type.as_str()).filter(|t| *t != "ixn" && !self.types.contains_key(*t)).collect::<Vec<_>>();
+        unknown.sort_unstable();
+        unknown.dedup();
+        unknown
+    }
+}
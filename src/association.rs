@@ -0,0 +1,390 @@
+use crate::converter;
+use crate::converter::Converter;
+use crate::error::CtdError;
+use crate::model::*;
+use horned_owl::model::*;
+use horned_owl::ontology;
+use horned_owl::vocab::WithIRI;
+use std::collections;
+
+/// A row of a CTD association table, converted to class axioms on its own.
+pub trait Association {
+    fn axioms(&self, converter: &AssociationConverter, build: &Build) -> Result<Vec<AnnotatedAxiom>, CtdError>;
+}
+
+/// Converts CTD association tables, resolving chemicals and genes the way `Converter` resolves interaction actors.
+///
+/// An association is stated on the classes, `SubClassOf(entity, property some other)`, annotated with the `dc:source` PMIDs and the evidence behind it.
+pub struct AssociationConverter<'a> {
+    converter: &'a Converter,
+}
+
+impl<'a> AssociationConverter<'a> {
+    pub fn new(converter: &'a Converter) -> AssociationConverter<'a> {
+        AssociationConverter { converter }
+    }
+
+    /// Converts a batch of rows into a single ontology, annotated as provided by CTD.
+    ///
+    /// Rows that fail to convert are left out of the ontology and returned alongside it.
+    pub fn build_ontology<A: Association, I: IntoIterator<Item = A>>(&self, associations: I) -> (ontology::axiom_mapped::AxiomMappedOntology, Vec<CtdError>) {
+        let build = Build::new();
+        let mut ontology = ontology::axiom_mapped::AxiomMappedOntology::default();
+        self.converter.header_axioms(&build).into_iter().for_each(|axiom| {
+            ontology.insert(axiom);
+        });
+        let mut rejects = Vec::new();
+        for association in associations {
+            match association.axioms(self, &build) {
+                Ok(axioms) => axioms.into_iter().for_each(|axiom| {
+                    ontology.insert(axiom);
+                }),
+                Err(e) => rejects.push(e),
+            }
+        }
+        (ontology, rejects)
+    }
+
+    /// The class of `id` under a registered actor type, labelled with `name` and linked as `Converter::class_link_axioms` does.
+    pub fn entity_class(&self, build: &Build, actor_type: &str, id: &str, name: &str, axioms: &mut Vec<AnnotatedAxiom>) -> Result<Class, CtdError> {
+        let registered = self.converter.config().actor_types.get(actor_type);
+        let actor_type = registered.ok_or_else(|| CtdError::UnregisteredActorType { actor_type: actor_type.to_string(), id: id.to_string() })?;
+        let resolved = self.converter.resolve_id(actor_type, id, name.to_string());
+        let class = build.class(resolved.class_iri.clone());
+        axioms.push(Axiom::DeclareClass(DeclareClass(class.clone())).into());
+        axioms.push(label(build, &class, name));
+        let link_axioms = self.converter.class_link_axioms(build, &class, &resolved.equivalent_class_iris, resolved.mapped_from.as_deref());
+        axioms.extend(link_axioms.into_iter().map(AnnotatedAxiom::from));
+        Ok(class)
+    }
+
+    /// The class of a `MESH:` or `OMIM:` disease id, labelled with `name`.
+    pub fn disease_class(&self, build: &Build, id: &str, name: &str, axioms: &mut Vec<AnnotatedAxiom>) -> Class {
+        let class = build.class(id.replace("MESH:", crate::MESH).replace("OMIM:", crate::OMIM));
+        axioms.push(Axiom::DeclareClass(DeclareClass(class.clone())).into());
+        axioms.push(label(build, &class, name));
+        class
+    }
+
+    /// The class of a `KEGG:` or `REACT:` pathway id, labelled with `name`.
+    pub fn pathway_class(&self, build: &Build, id: &str, name: &str, axioms: &mut Vec<AnnotatedAxiom>) -> Class {
+        let class = build.class(id.replace("KEGG:", crate::KEGG_PATHWAY).replace("REACT:", crate::REACTOME));
+        axioms.push(Axiom::DeclareClass(DeclareClass(class.clone())).into());
+        axioms.push(label(build, &class, name));
+        class
+    }
+
+    /// `SubClassOf(subject, property some object)`, annotated with `annotations` and a `dc:source` for each PMID.
+    pub fn association_axioms(&self, build: &Build, subject: &Class, property: &str, object: &Class, pm_ids: &[i32], annotations: Vec<Annotation>) -> Vec<AnnotatedAxiom> {
+        let property = build.object_property(property);
+        let sources =
+            pm_ids.iter().map(|pm_id| Annotation { ap: build.annotation_property(crate::DC_SOURCE.clone()), av: AnnotationValue::IRI(build.iri(converter::pm_id_iri(*pm_id))) });
+        let ann = sources.chain(annotations).collect::<collections::BTreeSet<_>>();
+        let mut axioms: Vec<AnnotatedAxiom> = vec![Axiom::DeclareObjectProperty(DeclareObjectProperty(property.clone())).into()];
+        if !pm_ids.is_empty() {
+            axioms.push(Axiom::DeclareAnnotationProperty(DeclareAnnotationProperty(build.annotation_property(crate::DC_SOURCE.clone()))).into());
+        }
+        axioms.push(AnnotatedAxiom::new(
+            SubClassOf { sub: subject.clone().into(), sup: ClassExpression::ObjectSomeValuesFrom { ope: property.into(), bce: Box::new(object.clone().into()) } },
+            ann,
+        ));
+        axioms
+    }
+
+    /// A CTD annotation property, declared and labelled, and its value as an axiom annotation.
+    pub fn evidence_annotation(&self, build: &Build, property: &str, property_label: &str, value: Literal, axioms: &mut Vec<AnnotatedAxiom>) -> Annotation {
+        axioms.extend(converter::build_annotation_property(build, property, property_label).into_iter().map(AnnotatedAxiom::from));
+        Annotation { ap: build.annotation_property(property), av: AnnotationValue::Literal(value) }
+    }
+
+    /// Curated associations with a disease, one per kind of direct evidence, or the association inferred through another entity.
+    pub fn disease_axioms(
+        &self,
+        build: &Build,
+        subject: &Class,
+        disease: &Class,
+        direct_evidence: &[DirectEvidence],
+        inference: Option<Inference>,
+        pm_ids: &[i32],
+    ) -> Vec<AnnotatedAxiom> {
+        let mut axioms = Vec::new();
+        let config = self.converter.config();
+        let properties = &config.properties;
+        // curated: subject SubClassOf (marker for | treats) some disease
+        for evidence in direct_evidence.iter() {
+            let property = match evidence {
+                DirectEvidence::MarkerMechanism => &properties.marker_mechanism,
+                DirectEvidence::Therapeutic => &properties.therapeutic,
+            };
+            let evidence_annotation = self.evidence_annotation(build, &config.ctd_term("direct_evidence"), "direct evidence", simple(evidence.code()), &mut axioms);
+            axioms.append(&mut self.association_axioms(build, subject, property, disease, pm_ids, vec![evidence_annotation]));
+        }
+        // inferred: subject SubClassOf (correlated with) some disease, with what it was inferred through
+        if let Some(inference) = inference {
+            let mut annotations = vec![self.evidence_annotation(build, &config.ctd_term(inference.term), inference.label, simple(inference.through), &mut axioms)];
+            if let Some(score) = inference.score {
+                annotations.push(self.evidence_annotation(build, &config.ctd_term("inference_score"), "inference score", double(build, score), &mut axioms));
+            }
+            axioms.append(&mut self.association_axioms(build, subject, &properties.inferred_association, disease, pm_ids, annotations));
+        }
+        axioms
+    }
+}
+
+/// The gene or chemical an association with a disease was inferred through, and CTD's score for the inference.
+pub struct Inference<'i> {
+    /// The name of the CTD annotation property naming it, e.g. `inference_gene`.
+    pub term: &'i str,
+    pub label: &'i str,
+    pub through: &'i str,
+    pub score: Option<f64>,
+}
+
+impl Association for ChemicalDisease {
+    fn axioms(&self, converter: &AssociationConverter, build: &Build) -> Result<Vec<AnnotatedAxiom>, CtdError> {
+        let mut axioms = Vec::new();
+        let chemical = converter.entity_class(build, "chemical", &self.chemical_id, &self.chemical_name, &mut axioms)?;
+        let disease = converter.disease_class(build, &self.disease_id, &self.disease_name, &mut axioms);
+        let inference =
+            self.inference_gene_symbol.as_ref().map(|gene_symbol| Inference { term: "inference_gene", label: "inference gene", through: gene_symbol, score: self.inference_score });
+        axioms.append(&mut converter.disease_axioms(build, &chemical, &disease, &self.direct_evidence, inference, &self.pm_ids));
+        Ok(axioms)
+    }
+}
+
+impl Association for GeneDisease {
+    fn axioms(&self, converter: &AssociationConverter, build: &Build) -> Result<Vec<AnnotatedAxiom>, CtdError> {
+        let mut axioms = Vec::new();
+        let gene = converter.entity_class(build, "gene", &self.gene_id, &self.gene_symbol, &mut axioms)?;
+        let disease = converter.disease_class(build, &self.disease_id, &self.disease_name, &mut axioms);
+        let inference = self.inference_chemical_name.as_ref().map(|chemical_name| Inference {
+            term: "inference_chemical",
+            label: "inference chemical",
+            through: chemical_name,
+            score: self.inference_score,
+        });
+        axioms.append(&mut converter.disease_axioms(build, &gene, &disease, &self.direct_evidence, inference, &self.pm_ids));
+        Ok(axioms)
+    }
+}
+
+impl Association for ChemicalPathway {
+    fn axioms(&self, converter: &AssociationConverter, build: &Build) -> Result<Vec<AnnotatedAxiom>, CtdError> {
+        let mut axioms = Vec::new();
+        let chemical = converter.entity_class(build, "chemical", &self.chemical_id, &self.chemical_name, &mut axioms)?;
+        let pathway = converter.pathway_class(build, &self.pathway_id, &self.pathway_name, &mut axioms);
+        // chemical SubClassOf (acts upstream of or within) some pathway, with the enrichment p-values
+        let config = converter.converter.config();
+        let annotations = vec![
+            converter.evidence_annotation(build, &config.ctd_term("p_value"), "p-value", double(build, self.p_value), &mut axioms),
+            converter.evidence_annotation(build, &config.ctd_term("corrected_p_value"), "corrected p-value", double(build, self.corrected_p_value), &mut axioms),
+        ];
+        let property = &config.properties.enriched_in_pathway;
+        axioms.append(&mut converter.association_axioms(build, &chemical, property, &pathway, &[], annotations));
+        Ok(axioms)
+    }
+}
+
+impl Association for GenePathway {
+    fn axioms(&self, converter: &AssociationConverter, build: &Build) -> Result<Vec<AnnotatedAxiom>, CtdError> {
+        let mut axioms = Vec::new();
+        let gene = converter.entity_class(build, "gene", &self.gene_id, &self.gene_symbol, &mut axioms)?;
+        let pathway = converter.pathway_class(build, &self.pathway_id, &self.pathway_name, &mut axioms);
+        // gene SubClassOf (participates in) some pathway
+        let property = &converter.converter.config().properties.participates_in;
+        axioms.append(&mut converter.association_axioms(build, &gene, property, &pathway, &[], Vec::new()));
+        Ok(axioms)
+    }
+}
+
+fn label(build: &Build, class: &Class, name: &str) -> AnnotatedAxiom {
+    Axiom::AnnotationAssertion(AnnotationAssertion::new(
+        class.0.clone(),
+        Annotation { ap: build.annotation_property(horned_owl::vocab::RDFS::Label.iri_s()), av: AnnotationValue::Literal(simple(name)) },
+    ))
+    .into()
+}
+
+fn simple(value: &str) -> Literal {
+    Literal::Simple { literal: value.to_string() }
+}
+
+fn double(build: &Build, value: f64) -> Literal {
+    Literal::Datatype { literal: value.to_string(), datatype_iri: build.iri(format!("{}double", crate::rdf::XSD)) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mapping::ChebiMeshMapping;
+    use itertools::Itertools;
+
+    /// An association as subject, property and object IRI, with its annotations as property IRI and value.
+    type Statement = (String, String, String, Vec<(String, String)>);
+
+    fn converter() -> Converter {
+        Converter::new(ChebiMeshMapping::from(collections::HashMap::from([(String::from("MESH:D000001"), String::from("CHEBI:1"))])))
+    }
+
+    fn statements(axioms: &[AnnotatedAxiom]) -> Vec<Statement> {
+        axioms
+            .iter()
+            .filter_map(|axiom| match &axiom.axiom {
+                Axiom::SubClassOf(SubClassOf {
+                    sub: ClassExpression::Class(sub),
+                    sup: ClassExpression::ObjectSomeValuesFrom { ope: ObjectPropertyExpression::ObjectProperty(op), bce },
+                }) => {
+                    let object = match bce.as_ref() {
+                        ClassExpression::Class(object) => object.0.to_string(),
+                        other => panic!("unexpected filler {:?}", other),
+                    };
+                    let annotations = axiom
+                        .ann
+                        .iter()
+                        .map(|a| {
+                            let value = match &a.av {
+                                AnnotationValue::IRI(iri) => iri.to_string(),
+                                AnnotationValue::Literal(Literal::Simple { literal }) | AnnotationValue::Literal(Literal::Datatype { literal, .. }) => literal.clone(),
+                                other => panic!("unexpected value {:?}", other),
+                            };
+                            (a.ap.0.to_string(), value)
+                        })
+                        .sorted()
+                        .collect();
+                    Some((sub.0.to_string(), op.0.to_string(), object, annotations))
+                }
+                _ => None,
+            })
+            .collect()
+    }
+
+    fn labels(axioms: &[AnnotatedAxiom]) -> Vec<(String, String)> {
+        axioms
+            .iter()
+            .filter_map(|axiom| match &axiom.axiom {
+                Axiom::AnnotationAssertion(AnnotationAssertion { subject, ann: Annotation { av: AnnotationValue::Literal(Literal::Simple { literal }), .. } }) => {
+                    Some((subject.to_string(), literal.clone()))
+                }
+                _ => None,
+            })
+            .sorted()
+            .dedup()
+            .collect()
+    }
+
+    fn iri(namespace: &str, local: &str) -> String {
+        format!("{}{}", namespace, local)
+    }
+
+    fn annotation(property: &str, value: &str) -> (String, String) {
+        (property.to_string(), value.to_string())
+    }
+
+    fn chemical_disease(direct_evidence: Vec<DirectEvidence>, inference_gene_symbol: Option<&str>) -> ChemicalDisease {
+        ChemicalDisease {
+            chemical_name: String::from("Calcimycin"),
+            chemical_id: String::from("MESH:D000001"),
+            cas_rn: None,
+            disease_name: String::from("Lung Neoplasms"),
+            disease_id: String::from("MESH:D008175"),
+            direct_evidence,
+            inference_gene_symbol: inference_gene_symbol.map(String::from),
+            inference_score: inference_gene_symbol.map(|_| 4.5),
+            omim_ids: Vec::new(),
+            pm_ids: vec![1, 2],
+        }
+    }
+
+    #[test]
+    fn curated_associations_are_stated_once_per_kind_of_evidence() {
+        let converter = converter();
+        let config = converter.config().clone();
+        let axioms =
+            chemical_disease(vec![DirectEvidence::MarkerMechanism, DirectEvidence::Therapeutic], None).axioms(&AssociationConverter::new(&converter), &Build::new()).unwrap();
+        let sources = [annotation(&crate::DC_SOURCE, &format!("{}/1", crate::PMID)), annotation(&crate::DC_SOURCE, &format!("{}/2", crate::PMID))];
+        let evidence = |code: &str| sources.iter().cloned().chain([annotation(&config.ctd_term("direct_evidence"), code)]).sorted().collect_vec();
+        let (chebi, disease) = (iri(crate::CHEBI, "1"), iri(crate::MESH, "D008175"));
+        assert_eq!(
+            statements(&axioms),
+            vec![
+                (chebi.clone(), crate::IS_MARKER_FOR.clone(), disease.clone(), evidence("marker/mechanism")),
+                (chebi.clone(), crate::IS_SUBSTANCE_THAT_TREATS.clone(), disease.clone(), evidence("therapeutic")),
+            ]
+        );
+        assert_eq!(
+            labels(&axioms),
+            vec![(disease, String::from("Lung Neoplasms")), (chebi, String::from("Calcimycin")), (config.ctd_term("direct_evidence"), String::from("direct evidence"))]
+        );
+    }
+
+    #[test]
+    fn inferred_associations_carry_what_they_were_inferred_through() {
+        let converter = converter();
+        let config = converter.config().clone();
+        let axioms = chemical_disease(Vec::new(), Some("CYP1B1")).axioms(&AssociationConverter::new(&converter), &Build::new()).unwrap();
+        let (_, property, _, annotations) = statements(&axioms).pop().unwrap();
+        assert_eq!(property, *crate::CORRELATED_WITH);
+        assert!(annotations.contains(&annotation(&config.ctd_term("inference_gene"), "CYP1B1")));
+        assert!(annotations.contains(&annotation(&config.ctd_term("inference_score"), "4.5")));
+
+        let gene_disease = GeneDisease {
+            gene_symbol: String::from("CYP1B1"),
+            gene_id: String::from("GENE:1545"),
+            disease_name: String::from("Glaucoma"),
+            disease_id: String::from("OMIM:231300"),
+            direct_evidence: Vec::new(),
+            inference_chemical_name: Some(String::from("Calcimycin")),
+            inference_score: None,
+            omim_ids: Vec::new(),
+            pm_ids: Vec::new(),
+        };
+        let axioms = gene_disease.axioms(&AssociationConverter::new(&converter), &Build::new()).unwrap();
+        let expected =
+            (iri(crate::NCBIGENE, "1545"), crate::CORRELATED_WITH.clone(), iri(crate::OMIM, "231300"), vec![annotation(&config.ctd_term("inference_chemical"), "Calcimycin")]);
+        assert_eq!(statements(&axioms), vec![expected]);
+    }
+
+    #[test]
+    fn pathway_rows() {
+        let converter = converter();
+        let config = converter.config().clone();
+        let chemical_pathway = ChemicalPathway {
+            chemical_name: String::from("Aflatoxin B1"),
+            chemical_id: String::from("MESH:D016604"),
+            cas_rn: None,
+            pathway_name: String::from("Apoptosis"),
+            pathway_id: String::from("KEGG:hsa04210"),
+            p_value: 1e-5,
+            corrected_p_value: 0.01,
+            target_match_qty: 3,
+            target_total_qty: 40,
+            background_match_qty: 90,
+            background_total_qty: 20000,
+        };
+        let axioms = chemical_pathway.axioms(&AssociationConverter::new(&converter), &Build::new()).unwrap();
+        // a chemical with no CHEBI mapping keeps its MESH class
+        let p_values = vec![annotation(&config.ctd_term("corrected_p_value"), "0.01"), annotation(&config.ctd_term("p_value"), "0.00001")];
+        assert_eq!(statements(&axioms), vec![(iri(crate::MESH, "D016604"), crate::ACTS_UPSTREAM_OF_OR_WITHIN.clone(), iri(crate::KEGG_PATHWAY, "hsa04210"), p_values)]);
+
+        let gene_pathway = GenePathway {
+            gene_symbol: String::from("CYP1B1"),
+            gene_id: String::from("GENE:1545"),
+            pathway_name: String::from("Metabolism"),
+            pathway_id: String::from("REACT:R-HSA-1430728"),
+        };
+        let axioms = gene_pathway.axioms(&AssociationConverter::new(&converter), &Build::new()).unwrap();
+        assert_eq!(statements(&axioms), vec![(iri(crate::NCBIGENE, "1545"), crate::PARTICIPATES_IN.clone(), iri(crate::REACTOME, "R-HSA-1430728"), Vec::new())]);
+        assert!(labels(&axioms).contains(&(iri(crate::REACTOME, "R-HSA-1430728"), String::from("Metabolism"))));
+    }
+
+    #[test]
+    fn unregistered_entity_types_are_an_error() {
+        let converter = converter();
+        let association_converter = AssociationConverter::new(&converter);
+        let error = association_converter.entity_class(&Build::new(), "protein", "PR:1", "P", &mut Vec::new()).unwrap_err();
+        assert_eq!(error, CtdError::UnregisteredActorType { actor_type: String::from("protein"), id: String::from("PR:1") });
+        let (ontology, rejects) = association_converter.build_ontology(vec![chemical_disease(vec![DirectEvidence::Therapeutic], None)]);
+        assert!(rejects.is_empty());
+        assert_eq!(statements(&ontology.i().iter().cloned().collect_vec()).len(), 1);
+    }
+}
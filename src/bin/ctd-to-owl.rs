@@ -1,546 +1,895 @@
 #[macro_use]
 extern crate log;
 
+use ctd_to_owl_rs::association::{Association, AssociationConverter};
+use ctd_to_owl_rs::config::Config;
+use ctd_to_owl_rs::converter::Converter;
+use ctd_to_owl_rs::diff;
+use ctd_to_owl_rs::error::CtdError;
+use ctd_to_owl_rs::filter;
+use ctd_to_owl_rs::filter::IxnFilter;
+use ctd_to_owl_rs::jsonl;
+use ctd_to_owl_rs::kgx;
+use ctd_to_owl_rs::mapping::{ChebiMeshMapping, MappingPolicy, MeshLink};
+use ctd_to_owl_rs::merge;
 use ctd_to_owl_rs::model::*;
+use ctd_to_owl_rs::parser;
+use ctd_to_owl_rs::rdf;
+use ctd_to_owl_rs::tsv;
+use ctd_to_owl_rs::validate;
+use ctd_to_owl_rs::vocabulary::Vocabulary;
 use horned_owl::io::owx;
-use horned_owl::model::*;
-use horned_owl::ontology;
-use horned_owl::vocab::WithIRI;
+use horned_owl::model::{Build, MutableOntology};
+use horned_owl::ontology::axiom_mapped::AxiomMappedOntology;
 use humantime::format_duration;
 use itertools::Itertools;
-use rayon::prelude::*;
 use std::collections;
 use std::error;
 use std::fs;
 use std::io;
+use std::io::Write;
 use std::path;
+use std::str;
+use std::sync;
+use std::sync::atomic;
 use std::time;
 use structopt::StructOpt;
-use xmltree::{Element, XMLNode};
 
 #[derive(StructOpt, Debug)]
 #[structopt(name = "ctd-to-owl-rs", about = "convert ctd xml to owx")]
 struct Options {
-    #[structopt(short = "i", long = "input", long_help = "input", required = true, parse(from_os_str))]
+    #[structopt(subcommand)]
+    command: Option<Command>,
+
+    #[structopt(short = "i", long = "input", long_help = "input (required unless a subcommand is given)", parse(from_os_str))]
+    input: Option<path::PathBuf>,
+
+    #[structopt(short = "o", long = "output", long_help = "output (required unless a subcommand is given)", parse(from_os_str))]
+    output: Option<path::PathBuf>,
+
+    #[structopt(short = "c", long = "chebi-to-mesh", long_help = "chebi to mesh tsv or sssom file (required unless a subcommand or --dump-json is given)", parse(from_os_str))]
+    chebi_to_mesh: Option<path::PathBuf>,
+
+    #[structopt(
+        long = "mapping-policy",
+        long_help = "what to do with a MESH id mapped to several CHEBI ids: first (use the first listed), skip-ambiguous (keep MESH) or all (keep MESH, equivalent to each CHEBI class)",
+        default_value = "first"
+    )]
+    mapping_policy: MappingPolicy,
+
+    #[structopt(
+        long = "mesh-link",
+        long_help = "keep the MESH class of chemicals mapped to CHEBI: none, equivalent (EquivalentClasses), exact-match (skos:exactMatch) or xref (oboInOwl:hasDbXref)",
+        default_value = "none"
+    )]
+    mesh_link: MeshLink,
+
+    #[structopt(long = "config", long_help = "json file overriding the built-in action code to class mapping, simple action codes and properties", parse(from_os_str))]
+    config: Option<path::PathBuf>,
+
+    #[structopt(
+        long = "chemical-vocabulary",
+        long_help = "CTD_chemicals.tsv; chemicals are labelled from it instead of the interaction text, and <output>/chemical_vocabulary.<format> holds their names, synonyms, CAS RNs, definitions and MeSH hierarchy",
+        parse(from_os_str)
+    )]
+    chemical_vocabulary: Option<path::PathBuf>,
+
+    #[structopt(
+        long = "gene-vocabulary",
+        long_help = "CTD_genes.tsv; genes are labelled with their official symbol from it instead of the interaction text, and <output>/gene_vocabulary.<format> holds their names, alternative symbols and UniProt and BioGRID cross-references",
+        parse(from_os_str)
+    )]
+    gene_vocabulary: Option<path::PathBuf>,
+
+    #[structopt(
+        short = "f",
+        long = "format",
+        long_help = "output format: owx (OWL/XML), ttl (Turtle), nt (N-Triples), nq (N-Quads) / trig (TriG) with one named graph per interaction, or kgx (Biolink nodes.tsv and edges.tsv)",
+        default_value = "owx"
+    )]
+    format: Format,
+
+    #[structopt(long = "merge", long_help = "write a single merged ontology (ctd.<format>) instead of one file per chunk")]
+    merge: bool,
+
+    #[structopt(long = "chunk-size", long_help = "number of interactions converted per parallel chunk", default_value = "40000")]
+    chunk_size: usize,
+
+    #[structopt(long = "skip-invalid", long_help = "log and skip interactions that fail to parse or convert instead of aborting the run")]
+    skip_invalid: bool,
+
+    #[structopt(long = "strict-actor-types", long_help = "fail interactions with an actor type that has no registry entry instead of skipping them")]
+    strict_actor_types: bool,
+
+    #[structopt(
+        long = "dump-json",
+        long_help = "write the parsed interactions to <output>/ixns.jsonl, one JSON object per line, instead of converting them; such a file is also accepted as --input"
+    )]
+    dump_json: bool,
+
+    #[structopt(long = "rejects", long_help = "tsv file recording the ixn id and error of every skipped interaction", requires = "skip-invalid", parse(from_os_str))]
+    rejects: Option<path::PathBuf>,
+
+    #[structopt(long = "taxon", long_help = "only convert interactions in this NCBI taxon, e.g. 9606; repeatable, @<file> reads a list", number_of_values = 1)]
+    taxon: Vec<String>,
+
+    #[structopt(long = "pmid", long_help = "only convert interactions citing this PubMed id; repeatable, @<file> reads a list", number_of_values = 1)]
+    pmid: Vec<String>,
+
+    #[structopt(
+        long = "chemical",
+        long_help = "only convert interactions with this chemical, e.g. MESH:D000009, at any nesting level; repeatable, @<file> reads a list",
+        number_of_values = 1
+    )]
+    chemical: Vec<String>,
+
+    #[structopt(
+        long = "gene",
+        long_help = "only convert interactions with this gene, e.g. GENE:1545, at any nesting level; repeatable, @<file> reads a list",
+        number_of_values = 1
+    )]
+    gene: Vec<String>,
+
+    #[structopt(long = "code", long_help = "only convert interactions with this action code, e.g. exp; repeatable, @<file> reads a list", number_of_values = 1)]
+    code: Vec<String>,
+
+    #[structopt(long = "degree", long_help = "only convert interactions with this degree code (+, -, 0, 1 or ^); repeatable, @<file> reads a list", number_of_values = 1)]
+    degree: Vec<String>,
+}
+
+#[derive(StructOpt, Debug)]
+enum Command {
+    /// Compare two CTD releases by ixn id, optionally converting only the changed interactions
+    #[structopt(name = "diff")]
+    Diff(DiffOptions),
+    /// Check the structure of a CTD xml file before converting it, printing violations with line numbers as tsv
+    #[structopt(name = "validate")]
+    Validate(ValidateOptions),
+    /// Convert CTD_chemicals_diseases.tsv into curated and inferred chemical–disease associations
+    #[structopt(name = "chemical-disease")]
+    ChemicalDisease(TableOptions),
+    /// Convert CTD_genes_diseases.tsv into curated and inferred gene–disease associations
+    #[structopt(name = "gene-disease")]
+    GeneDisease(TableOptions),
+    /// Convert CTD_chem_pathways_enriched.tsv into chemical–pathway enrichments
+    #[structopt(name = "chemical-pathway")]
+    ChemicalPathway(TableOptions),
+    /// Convert CTD_genes_pathways.tsv into gene–pathway annotations
+    #[structopt(name = "gene-pathway")]
+    GenePathway(TableOptions),
+}
+
+#[derive(StructOpt, Debug)]
+struct TableOptions {
+    #[structopt(short = "i", long = "input", long_help = "CTD association table, optionally gzip-compressed", parse(from_os_str))]
     input: path::PathBuf,
 
-    #[structopt(short = "o", long = "output", long_help = "output", required = true, parse(from_os_str))]
+    #[structopt(short = "o", long = "output", long_help = "output directory", parse(from_os_str))]
     output: path::PathBuf,
 
-    #[structopt(short = "c", long = "chebi-to-mesh", long_help = "chebi to mesh tsv file", required = true, parse(from_os_str))]
-    chebi_to_mesh: path::PathBuf,
+    #[structopt(short = "c", long = "chebi-to-mesh", long_help = "chebi to mesh tsv or sssom file; without it chemicals keep their MESH class", parse(from_os_str))]
+    chebi_to_mesh: Option<path::PathBuf>,
+
+    #[structopt(
+        long = "mapping-policy",
+        long_help = "what to do with a MESH id mapped to several CHEBI ids: first (use the first listed), skip-ambiguous (keep MESH) or all (keep MESH, equivalent to each CHEBI class)",
+        default_value = "first"
+    )]
+    mapping_policy: MappingPolicy,
+
+    #[structopt(
+        long = "mesh-link",
+        long_help = "keep the MESH class of chemicals mapped to CHEBI: none, equivalent (EquivalentClasses), exact-match (skos:exactMatch) or xref (oboInOwl:hasDbXref)",
+        default_value = "none"
+    )]
+    mesh_link: MeshLink,
+
+    #[structopt(long = "config", long_help = "json file overriding the built-in properties and actor types", parse(from_os_str))]
+    config: Option<path::PathBuf>,
+
+    #[structopt(short = "f", long = "format", long_help = "output format: owx, ttl or nt", default_value = "owx")]
+    format: Format,
+
+    #[structopt(long = "chunk-size", long_help = "number of rows converted per parallel chunk, each written to its own file", default_value = "40000")]
+    chunk_size: usize,
+
+    #[structopt(long = "skip-invalid", long_help = "log and skip rows that fail to parse instead of aborting the run")]
+    skip_invalid: bool,
+
+    #[structopt(long = "rejects", long_help = "tsv file recording the error of every skipped row", requires = "skip-invalid", parse(from_os_str))]
+    rejects: Option<path::PathBuf>,
 }
-fn main() -> Result<(), Box<dyn error::Error>> {
-    let start = time::Instant::now();
-    env_logger::init();
-    let options = Options::from_args();
-    debug!("{:?}", options);
 
-    let chebi_to_mesh_map: collections::HashMap<String, String> = fs::read_to_string(&options.chebi_to_mesh)?
-        .lines()
-        .map(|a| a.split("\t").map(str::to_owned).collect_vec())
-        .map(|vec| {
-            assert_eq!(vec.len(), 2);
-            (vec[1].to_string(), vec[0].to_string())
-        })
-        .collect();
-
-    let model = ctd_input_to_model(&options.input)?;
-
-    let mut prefix_mapping = curie::PrefixMapping::default();
-    prefix_mapping.add_prefix("owl", "http://www.w3.org/2002/07/owl#").unwrap();
-    prefix_mapping.add_prefix("rdf", "http://www.w3.org/1999/02/22-rdf-syntax-ns#").unwrap();
-    prefix_mapping.add_prefix("xml", "http://www.w3.org/XML/1998/namespace").unwrap();
-    prefix_mapping.add_prefix("xsd", "http://www.w3.org/2001/XMLSchema#").unwrap();
-    prefix_mapping.add_prefix("rdfs", "http://www.w3.org/2000/01/rdf-schema#").unwrap();
-    prefix_mapping.add_prefix("CHEBI", ctd_to_owl_rs::CHEBI).unwrap();
-    prefix_mapping.add_prefix("PMID", ctd_to_owl_rs::PMID).unwrap();
-    prefix_mapping.add_prefix("MESH", ctd_to_owl_rs::MESH).unwrap();
-    prefix_mapping.add_prefix("NCBITaxon", ctd_to_owl_rs::NCBI_TAXON).unwrap();
-    prefix_mapping.add_prefix("NCBIGENE", ctd_to_owl_rs::NCBIGENE).unwrap();
-    prefix_mapping.add_prefix("DC", ctd_to_owl_rs::DC).unwrap();
-
-    let output_dir: path::PathBuf = options.output;
-    fs::create_dir_all(&output_dir)?;
+#[derive(StructOpt, Debug)]
+struct ValidateOptions {
+    #[structopt(long_help = "structured xml to check", parse(from_os_str))]
+    input: path::PathBuf,
 
-    model.par_chunks(40000).enumerate().for_each(|(idx, model_chunk)| {
-        let ontology = build_ontology(model_chunk.to_vec(), &chebi_to_mesh_map).unwrap();
-        let output_path = output_dir.clone().join(format!("{}.owx", idx));
-        let output = fs::File::create(&output_path).unwrap();
-        info!("writing: {:?}", output_path);
-        let mut buf_writer = io::BufWriter::new(output);
-        owx::writer::write(&mut buf_writer, &ontology, Some(&prefix_mapping)).unwrap();
-    });
+    #[structopt(long = "config", long_help = "json file whose action codes and actor types are allowed in addition to the built-in ones", parse(from_os_str))]
+    config: Option<path::PathBuf>,
+}
+
+#[derive(StructOpt, Debug)]
+struct DiffOptions {
+    #[structopt(long_help = "structured xml of the previous release", parse(from_os_str))]
+    old: path::PathBuf,
 
-    // let ontology = build_ontology(model, &chebi_to_mesh_map).unwrap();
-    // let output = fs::File::create(&options.output).unwrap();
-    // info!("writing: {:?}", &options.output);
-    // let mut buf_writer = io::BufWriter::new(output);
-    // owx::writer::write(&mut buf_writer, &ontology, Some(&prefix_mapping)).unwrap();
+    #[structopt(long_help = "structured xml of the new release", parse(from_os_str))]
+    new: path::PathBuf,
 
-    info!("Duration: {}", format_duration(start.elapsed()).to_string());
-    Ok(())
+    #[structopt(short = "o", long = "output", long_help = "output directory for diff.tsv and the delta", parse(from_os_str))]
+    output: path::PathBuf,
+
+    #[structopt(
+        long = "delta",
+        long_help = "also write added.<format> with the axioms of added and changed interactions, and for removed and changed ones removed.<format> (nq, trig) or removed.tsv with their interaction IRIs (owx, ttl, nt)",
+        requires = "chebi-to-mesh"
+    )]
+    delta: bool,
+
+    #[structopt(short = "c", long = "chebi-to-mesh", long_help = "chebi to mesh tsv or sssom file", parse(from_os_str))]
+    chebi_to_mesh: Option<path::PathBuf>,
+
+    #[structopt(
+        long = "mapping-policy",
+        long_help = "what to do with a MESH id mapped to several CHEBI ids: first (use the first listed), skip-ambiguous (keep MESH) or all (keep MESH, equivalent to each CHEBI class)",
+        default_value = "first"
+    )]
+    mapping_policy: MappingPolicy,
+
+    #[structopt(
+        long = "mesh-link",
+        long_help = "keep the MESH class of chemicals mapped to CHEBI: none, equivalent (EquivalentClasses), exact-match (skos:exactMatch) or xref (oboInOwl:hasDbXref)",
+        default_value = "none"
+    )]
+    mesh_link: MeshLink,
+
+    #[structopt(long = "config", long_help = "json file overriding the built-in action code to class mapping, simple action codes and properties", parse(from_os_str))]
+    config: Option<path::PathBuf>,
+
+    #[structopt(short = "f", long = "format", long_help = "delta format: owx, ttl, nt, nq or trig", default_value = "owx")]
+    format: Format,
+
+    #[structopt(long = "skip-invalid", long_help = "log and skip interactions of the delta that fail to convert instead of aborting")]
+    skip_invalid: bool,
+
+    #[structopt(long = "strict-actor-types", long_help = "fail interactions of the delta with an actor type that has no registry entry instead of skipping them")]
+    strict_actor_types: bool,
 }
 
-fn build_ontology(model: Vec<IXN>, chebi_to_mesh_map: &collections::HashMap<String, String>) -> Result<ontology::axiom_mapped::AxiomMappedOntology, Box<dyn error::Error>> {
-    let build = horned_owl::model::Build::new();
-    let mut ontology = ontology::axiom_mapped::AxiomMappedOntology::default();
-    let provided_by_ap = build.annotation_property("http://purl.org/pav/providedBy");
-    let ontology_root_iri = build.iri("http://ctdbase.org");
-    ontology.insert(Axiom::DeclareAnnotationProperty(DeclareAnnotationProperty { 0: provided_by_ap.clone() }));
-    ontology.insert(Axiom::OntologyAnnotation(OntologyAnnotation { 0: Annotation { ap: provided_by_ap.clone(), av: AnnotationValue::IRI(ontology_root_iri.clone()) } }));
-
-    for ixn in model.iter() {
-        let graph_iri = build.iri(format!("{}{}", ctd_to_owl_rs::CTDIXN, ixn.id));
-        ontology.insert(Axiom::AnnotationAssertion(AnnotationAssertion::new(
-            graph_iri.clone(),
-            Annotation { ap: provided_by_ap.clone(), av: AnnotationValue::IRI(ontology_root_iri.clone()) },
-        )));
-
-        for (taxon_idx, taxon) in ixn.taxon.iter().enumerate() {
-            let ixn_individual_iri = build.iri(format!("{}#{}", graph_iri.to_string(), taxon_idx));
-            debug!("ixn_individual_iri: {:?}", ixn_individual_iri);
-            ontology.insert(Axiom::DeclareNamedIndividual(DeclareNamedIndividual { 0: ixn_individual_iri.clone().into() }));
-
-            match process_actor(&build, ixn, &taxon_idx, &taxon, &ixn_individual_iri, chebi_to_mesh_map, &ixn.axns, &ixn.actors) {
-                Some((_, actor_axioms)) => {
-                    debug!("using ixn: {}", ixn.id);
-                    actor_axioms.into_iter().for_each(|axiom| {
-                        ontology.insert(axiom);
-                    });
-                }
-                _ => {
-                    debug!("skipping ixn: {}", ixn.id);
-                }
-            }
+impl Options {
+    /// Combines the selection options; different options must all match, repeated values of one option are alternatives.
+    fn ixn_filter(&self) -> Result<IxnFilter, Box<dyn error::Error>> {
+        let parse_i32 = |values: &[String], name: &str| -> Result<collections::HashSet<i32>, Box<dyn error::Error>> {
+            filter::expand_values(values)?.iter().map(|v| v.parse::<i32>().map_err(|e| format!("--{} {:?}: {}", name, v, e).into())).collect()
+        };
+        let mut ixn_filter = IxnFilter { taxa: parse_i32(&self.taxon, "taxon")?, pm_ids: parse_i32(&self.pmid, "pmid")?, ..IxnFilter::default() };
+        ixn_filter.actor_ids.insert(String::from("chemical"), filter::expand_values(&self.chemical)?.into_iter().collect());
+        ixn_filter.actor_ids.insert(String::from("gene"), filter::expand_values(&self.gene)?.into_iter().collect());
+        ixn_filter.codes = filter::expand_values(&self.code)?.into_iter().collect();
+        for degree in filter::expand_values(&self.degree)? {
+            let mut chars = degree.chars();
+            match (chars.next(), chars.next()) {
+                (Some(c), None) => ixn_filter.degree_codes.insert(c),
+                _ => return Err(format!("--degree {:?}: expected a single character", degree).into()),
+            };
         }
+        Ok(ixn_filter)
     }
-    Ok(ontology)
 }
 
-fn process_actor(
-    build: &Build,
-    ixn: &IXN,
-    taxon_idx: &usize,
-    taxon: &Taxon,
-    ixn_individual_iri: &IRI,
-    chebi_to_mesh_map: &collections::HashMap<String, String>,
-    axns: &Vec<AXN>,
-    actors: &Vec<Actor>,
-) -> Option<(NamedIndividual, Vec<Axiom>)> {
-    let codes = axns.iter().map(|a| a.code.clone()).collect_vec();
-
-    if codes.iter().all(|p| p.as_str() == "w" && actors.iter().all(|a| a.actor_type.as_str() != "ixn")) {
-        // cotreatment
-        let mut axioms: Vec<Axiom> = Vec::new();
-        actors.iter().for_each(|actor| {
-            let (actor_individual, mut atomic_actor_axioms) =
-                get_local_individual_and_axioms(build, actor, taxon_idx, chebi_to_mesh_map).expect("could not get actor class and entity");
-            axioms.append(&mut atomic_actor_axioms);
-            axioms.append(&mut build_object_property_assertion(&build.object_property(ctd_to_owl_rs::HAS_INPUT.clone()), ixn_individual_iri, &actor_individual).unwrap());
-            axioms.append(&mut build_class_assertion(&build.class(ctd_to_owl_rs::COTREATMENT.clone()), ixn_individual_iri).unwrap());
-            let mut remnant_axioms = add_remnants(build, ixn, taxon, &ixn_individual_iri).unwrap();
-            axioms.append(&mut remnant_axioms);
-        });
-        return Some((ixn_individual_iri.clone().into(), axioms));
-    }
-
-    if codes.iter().all(|p| p.as_str() == "b" && actors.iter().all(|a| a.actor_type.as_str() != "ixn")) {
-        // binding
-        let mut axioms: Vec<Axiom> = Vec::new();
-        actors.iter().for_each(|actor| {
-            let (actor_individual, mut atomic_actor_axioms) =
-                get_local_individual_and_axioms(build, actor, taxon_idx, chebi_to_mesh_map).expect("could not get actor class and entity");
-            axioms.append(&mut atomic_actor_axioms);
-            axioms.append(&mut build_object_property_assertion(&build.object_property(ctd_to_owl_rs::HAS_INPUT.clone()), ixn_individual_iri, &actor_individual).unwrap());
-            axioms.append(&mut build_class_assertion(&build.class(ctd_to_owl_rs::BINDING.clone()), ixn_individual_iri).unwrap());
-            let mut remnant_axioms = add_remnants(build, ixn, taxon, &ixn_individual_iri).unwrap();
-            axioms.append(&mut remnant_axioms);
-        });
-        return Some((ixn_individual_iri.clone().into(), axioms));
-    }
-
-    if codes.iter().all(|p| p.as_str() == "rxn") && actors.len() == 2 && actors[1].actor_type == "ixn" {
-        let mut axioms: Vec<Axiom> = Vec::new();
-
-        let subject = &actors[0];
-        let results = match subject.actor_type.as_str() {
-            "ixn" => match process_actor(build, ixn, taxon_idx, taxon, ixn_individual_iri, chebi_to_mesh_map, &subject.axns, &subject.actors) {
-                Some((subject_individual, subject_axioms)) => Some((subject_individual, subject_axioms)),
-                None => None,
-            },
-            _ => {
-                let (subject_individual, subject_axioms) =
-                    get_local_individual_and_axioms(build, subject, taxon_idx, chebi_to_mesh_map).expect("could not get actor class and entity");
-                Some((subject_individual, subject_axioms))
-            }
-        };
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Format {
+    Owx,
+    Ttl,
+    Nt,
+    Nq,
+    Trig,
+    Kgx,
+}
 
-        match results {
-            Some((subject_individual, subject_axioms)) => {
-                let target = &actors[1];
-                match process_actor(build, ixn, taxon_idx, taxon, ixn_individual_iri, chebi_to_mesh_map, &target.axns, &target.actors) {
-                    Some((_, target_axioms)) => {
-                        debug!("rxn - target.id: {:?}", target.id);
-
-                        axioms.append(&mut target_axioms.clone());
-                        axioms.append(&mut subject_axioms.clone());
-                        let subject_process_iri = build.iri(format!("{}-process", subject_individual.0));
-                        axioms.push(Axiom::DeclareNamedIndividual(DeclareNamedIndividual { 0: subject_process_iri.clone().into() }));
-
-                        let (target_individual, target_axioms) = process_actor(build, ixn, taxon_idx, taxon, ixn_individual_iri, chebi_to_mesh_map, &target.axns, &target.actors)
-                            .expect(format!("failed to process actor: {:?}", target).as_str());
-                        axioms.append(&mut target_axioms.clone());
-
-                        let process_class = build.class(ctd_to_owl_rs::PROCESS.clone());
-                        axioms.push(Axiom::DeclareClass(DeclareClass { 0: process_class.clone() }));
-                        axioms.push(Axiom::ClassAssertion(ClassAssertion { ce: process_class.clone().into(), i: ixn_individual_iri.clone().into() }));
-
-                        let part_of_class = build.object_property(ctd_to_owl_rs::PART_OF.clone());
-                        axioms.push(Axiom::DeclareObjectProperty(DeclareObjectProperty { 0: part_of_class.clone() }));
-                        axioms.push(Axiom::ObjectPropertyAssertion(ObjectPropertyAssertion::new(
-                            part_of_class.into(),
-                            target_individual.clone(),
-                            ixn_individual_iri.clone().into(),
-                        )));
-
-                        let axn = ixn.axns.iter().next().expect("could not get AXN from IXN");
-                        let process_to_process_op = process_to_process(&build, &axn.degree_code);
-                        axioms.push(Axiom::DeclareObjectProperty(DeclareObjectProperty { 0: process_to_process_op.clone() }));
-                        axioms.push(Axiom::ObjectPropertyAssertion(ObjectPropertyAssertion::new(
-                            process_to_process_op.into(),
-                            subject_process_iri.clone().into(),
-                            target_individual.clone(),
-                        )));
-                    }
-                    None => {
-                        debug!("failed to process actor: {:?}", target)
-                    }
-                };
-            }
-            None => {}
+impl Format {
+    fn extension(&self) -> &'static str {
+        match self {
+            Format::Owx => "owx",
+            Format::Ttl => "ttl",
+            Format::Nt => "nt",
+            Format::Nq => "nq",
+            Format::Trig => "trig",
+            Format::Kgx => "tsv",
         }
+    }
 
-        return Some((ixn_individual_iri.clone().into(), axioms));
+    /// Dataset formats are written one interaction graph at a time rather than from a built ontology.
+    fn dataset(&self) -> Option<rdf::DatasetFormat> {
+        match self {
+            Format::Nq => Some(rdf::DatasetFormat::NQuads),
+            Format::Trig => Some(rdf::DatasetFormat::TriG),
+            _ => None,
+        }
     }
 
-    if codes.iter().any(|p| ctd_to_owl_rs::AXN_CODES.contains(&p.as_str())) && actors.len() == 2 && actors[1].actor_type != "ixn" {
-        let mut axioms: Vec<Axiom> = Vec::new();
+    fn write<W: io::Write>(&self, write: &mut W, ontology: &AxiomMappedOntology, prefix_mapping: &curie::PrefixMapping) -> Result<(), Box<dyn error::Error>> {
+        match self {
+            Format::Owx => owx::writer::write(write, ontology, Some(prefix_mapping))?,
+            Format::Ttl => rdf::write_turtle(write, ontology, prefix_mapping)?,
+            Format::Nt => rdf::write_ntriples(write, ontology)?,
+            Format::Nq | Format::Trig | Format::Kgx => return Err(format!("{} output is written per interaction, not from an ontology", self.extension()).into()),
+        }
+        Ok(())
+    }
 
-        let subject = &actors[0];
-        let results = match subject.actor_type.as_str() {
-            "ixn" => match process_actor(build, ixn, taxon_idx, taxon, ixn_individual_iri, chebi_to_mesh_map, &subject.axns, &subject.actors) {
-                Some((subject_individual, subject_axioms)) => Some((subject_individual, subject_axioms)),
-                None => None,
-            },
-            _ => {
-                let (subject_individual, subject_axioms) =
-                    get_local_individual_and_axioms(build, subject, taxon_idx, chebi_to_mesh_map).expect("could not get actor class and entity");
-                Some((subject_individual, subject_axioms))
-            }
-        };
+    /// Serializes the ontology of one chunk of a merged document, on the worker that built it.
+    fn chunk(&self, ontology: &AxiomMappedOntology, idx: usize, prefix_mapping: &curie::PrefixMapping) -> Result<merge::Chunk, Box<dyn error::Error>> {
+        match self {
+            Format::Owx => merge::Chunk::owx(ontology, prefix_mapping),
+            Format::Ttl | Format::Nt => merge::Chunk::triples(ontology, &format!("c{}", idx)),
+            Format::Nq | Format::Trig | Format::Kgx => Err(format!("{} output is not merged from ontologies", self.extension()).into()),
+        }
+    }
 
-        match results {
-            Some((subject_individual, subject_axioms)) => {
-                let subject_process = build.named_individual(format!("{}-process", subject_individual.0));
-                axioms.push(Axiom::DeclareNamedIndividual(DeclareNamedIndividual { 0: subject_individual.clone() }));
-                axioms.push(Axiom::DeclareNamedIndividual(DeclareNamedIndividual { 0: subject_process.clone() }));
-
-                axioms.append(&mut build_class_assertion(&build.class(ctd_to_owl_rs::PROCESS.clone()), &subject_process.0).unwrap());
-                axioms
-                    .append(&mut build_object_property_assertion(&build.object_property(ctd_to_owl_rs::HAS_PARTICIPANT.clone()), &subject_process.0, &subject_individual).unwrap());
-
-                axioms.append(&mut subject_axioms.clone());
-                let axn = ixn.axns.iter().next().expect("could not get AXN from IXN");
-
-                let target = &actors[1];
-                let (target_individual, target_axioms) =
-                    get_local_individual_and_axioms(build, &target, taxon_idx, chebi_to_mesh_map).expect("could not get actor class and entity");
-                axioms.append(&mut target_axioms.clone());
-                let class_map = ctd_to_owl_rs::get_class_map();
-                codes.iter().filter(|code| class_map.contains_key(code.as_str())).enumerate().for_each(|(idx, code)| {
-                    let ixn_type = class_map.get(code).expect(format!("class not found for code: {:?}", code).as_str());
-                    let ixn_type_class = build.class(ixn_type);
-                    let local_ixn_iri = build.iri(format!("{}{}#{}-target-{}", ctd_to_owl_rs::CTDIXN, ixn.id, taxon_idx, idx));
-
-                    axioms.append(&mut build_class_assertion(&ixn_type_class, &local_ixn_iri).unwrap());
-
-                    axioms.push(Axiom::DeclareNamedIndividual(DeclareNamedIndividual { 0: local_ixn_iri.clone().into() }));
-
-                    axioms.push(Axiom::ObjectPropertyAssertion(ObjectPropertyAssertion::new(
-                        build.object_property(ctd_to_owl_rs::HAS_PARTICIPANT.clone()).into(),
-                        local_ixn_iri.clone().into(),
-                        target_individual.clone(),
-                    )));
-
-                    let process_to_process_op = process_to_process(&build, &axn.degree_code);
-                    axioms.append(&mut build_object_property_assertion(&process_to_process_op, &subject_process.0, &local_ixn_iri.clone().into()).unwrap());
-
-                    let part_of_op = build.object_property(ctd_to_owl_rs::PART_OF.clone());
-                    axioms.push(Axiom::DeclareObjectProperty(DeclareObjectProperty { 0: part_of_op.clone() }));
-                    axioms.push(Axiom::ObjectPropertyAssertion(ObjectPropertyAssertion::new(part_of_op.into(), local_ixn_iri.clone().into(), ixn_individual_iri.clone().into())));
-                });
-                let mut remnant_axioms = add_remnants(build, ixn, taxon, &ixn_individual_iri).unwrap();
-                axioms.append(&mut remnant_axioms);
-            }
-            None => {}
+    /// Writes `header` and the chunks of a merged document, in order, as one document.
+    fn write_merged<W: io::Write>(
+        &self,
+        write: &mut W,
+        header: &AxiomMappedOntology,
+        chunks: Vec<merge::Chunk>,
+        prefix_mapping: &curie::PrefixMapping,
+    ) -> Result<(), Box<dyn error::Error>> {
+        match self {
+            Format::Owx => merge::write_owx(write, header, prefix_mapping, chunks),
+            Format::Ttl => merge::write_turtle(write, header, prefix_mapping, chunks),
+            Format::Nt => merge::write_ntriples(write, header, chunks),
+            Format::Nq | Format::Trig | Format::Kgx => Err(format!("{} output is not merged from ontologies", self.extension()).into()),
         }
-        return Some((ixn_individual_iri.clone().into(), axioms));
     }
+}
 
-    debug!("not using ixn: {:?}", ixn.id);
-    None
+impl str::FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Format, String> {
+        match s {
+            "owx" => Ok(Format::Owx),
+            "ttl" => Ok(Format::Ttl),
+            "nt" => Ok(Format::Nt),
+            "nq" => Ok(Format::Nq),
+            "trig" => Ok(Format::Trig),
+            "kgx" => Ok(Format::Kgx),
+            _ => Err(format!("unknown format: {} (expected owx, ttl, nt, nq, trig or kgx)", s)),
+        }
+    }
 }
 
-fn get_local_individual_and_axioms(
-    build: &Build,
-    actor: &Actor,
-    taxon_idx: &usize,
-    chebi_to_mesh_map: &collections::HashMap<String, String>,
-) -> Result<(NamedIndividual, Vec<Axiom>), Box<dyn error::Error>> {
-    let (actor_class, actor_entity, actor_text, actor_label) = match actor.actor_type.as_str() {
-        "chemical" => {
-            let chebi_mapping = chebi_to_mesh_map.get(actor.id.as_str());
-            let actor_class = match chebi_mapping {
-                Some(c) => build.class(c.replace("CHEBI:", ctd_to_owl_rs::CHEBI)),
-                None => {
-                    debug!("no mapping for: {:?}", actor.id);
-                    build.class(actor.id.replace("MESH:", format!("{}", ctd_to_owl_rs::MESH).as_str()))
-                }
-            };
+/// Interactions that failed to parse or convert, shared between the reading thread and the conversion workers.
+struct Rejects {
+    skip_invalid: bool,
+    count: atomic::AtomicUsize,
+    writer: sync::Mutex<Option<io::BufWriter<fs::File>>>,
+    failure: sync::Mutex<Option<CtdError>>,
+}
 
-            let actor_text = match &actor.text {
-                Some(t) => t.clone(),
-                None => {
-                    warn!("text is empty - actor: {:?}", actor);
-                    String::from("")
-                }
-            };
-            let label = format!("{}#{}-{}", actor_text, actor.parent_id, actor.position);
-            (actor_class, build.class(ctd_to_owl_rs::CHEMICAL_ENTITY.clone()), actor_text.clone(), label)
-        }
-        "gene" => {
-            let actor_class = build.class(actor.id.replace("GENE:", ctd_to_owl_rs::NCBIGENE));
-            let (actor_text, label) = match &actor.text {
-                Some(t) => (t.clone(), format!("{}#{}-{}", t, actor.parent_id, actor.position)),
-                None => {
-                    let seq_id_value = match &actor.seq_id {
-                        Some(s) => s.clone(),
-                        None => {
-                            warn!("seq_id is empty - actor: {:?}", actor);
-                            String::from("")
-                        }
-                    };
-                    (seq_id_value.clone(), format!("{}#{}-{}", seq_id_value, actor.parent_id, actor.position))
-                }
-            };
-            (actor_class, build.class(ctd_to_owl_rs::GENE_ENTITY.clone()), actor_text, label)
+impl Rejects {
+    fn new(skip_invalid: bool, rejects_path: Option<&path::Path>) -> Result<Rejects, Box<dyn error::Error>> {
+        let writer = match rejects_path {
+            Some(p) => Some(io::BufWriter::new(fs::File::create(p)?)),
+            None => None,
+        };
+        Ok(Rejects { skip_invalid, count: atomic::AtomicUsize::new(0), writer: sync::Mutex::new(writer), failure: sync::Mutex::new(None) })
+    }
+
+    /// Records a bad interaction; without `--skip-invalid` the first one becomes the failure of the whole run.
+    fn reject(&self, e: CtdError) {
+        if !self.skip_invalid {
+            error!("{}", e);
+            self.failure.lock().unwrap().get_or_insert(e);
+            return;
         }
-        _ => {
-            panic!("should never get here")
+        warn!("skipping: {}", e);
+        self.count.fetch_add(1, atomic::Ordering::Relaxed);
+        if let Some(writer) = self.writer.lock().unwrap().as_mut() {
+            let ixn_id = e.ixn_id().map(|id| id.to_string()).unwrap_or_default();
+            if let Err(write_error) = writeln!(writer, "{}\t{}", ixn_id, e) {
+                warn!("could not write reject: {}", write_error);
+            }
         }
-    };
+    }
 
-    let mut axioms: Vec<Axiom> = Vec::new();
-
-    let actor_individual_iri = build.iri(format!("{}{}#{}-{}", ctd_to_owl_rs::CTDIXN, actor.parent_id, taxon_idx, actor.position));
-    axioms.push(Axiom::DeclareNamedIndividual(DeclareNamedIndividual { 0: actor_individual_iri.clone().into() }));
-
-    // actorInd Type actorClass,
-    axioms.append(&mut build_class_assertion(&actor_class, &actor_individual_iri)?);
-
-    // actorClass Annotation(RDFSLabel, typeLabel),
-    axioms.push(Axiom::AnnotationAssertion(AnnotationAssertion::new(
-        actor_class.0.clone(),
-        Annotation { ap: build.annotation_property(horned_owl::vocab::RDFS::Label.iri_s()), av: AnnotationValue::Literal(Literal::Simple { literal: actor_text.clone() }) },
-    )));
-
-    // actorInd Type nodeType,
-    axioms.append(&mut build_class_assertion(&actor_entity, &actor_individual_iri)?);
-
-    // actorInd Annotation(RDFSLabel, label)
-    axioms.push(Axiom::AnnotationAssertion(AnnotationAssertion::new(
-        actor_individual_iri.clone(),
-        Annotation { ap: build.annotation_property(horned_owl::vocab::RDFS::Label.iri_s()), av: AnnotationValue::Literal(Literal::Simple { literal: actor_label }) },
-    )));
-
-    match &actor.form {
-        Some(s) => {
-            let form_ap = build.annotation_property("http://ctd.example.org/has_form");
-            axioms.push(Axiom::DeclareAnnotationProperty(DeclareAnnotationProperty { 0: form_ap.clone() }));
-            axioms.push(Axiom::AnnotationAssertion(AnnotationAssertion::new(
-                actor_individual_iri.clone(),
-                Annotation { ap: form_ap.clone(), av: AnnotationValue::Literal(Literal::Simple { literal: s.to_string() }) },
-            )));
+    /// Records an error a worker cannot recover from, such as a failed write, as the failure of the whole run whatever `--skip-invalid` says.
+    fn fail(&self, e: Box<dyn error::Error>) {
+        error!("{}", e);
+        self.failure.lock().unwrap().get_or_insert(CtdError::Io { message: e.to_string() });
+    }
+
+    fn failed(&self) -> bool {
+        self.failure.lock().unwrap().is_some()
+    }
+
+    fn finish(self) -> Result<usize, Box<dyn error::Error>> {
+        if let Some(e) = self.failure.into_inner().unwrap() {
+            return Err(e.into());
         }
-        _ => {}
-    };
-    Ok((actor_individual_iri.into(), axioms))
+        if let Some(mut writer) = self.writer.into_inner().unwrap() {
+            writer.flush()?;
+        }
+        Ok(self.count.into_inner())
+    }
 }
 
-fn add_remnants(build: &Build, ixn: &IXN, taxon: &Taxon, ixn_individual_iri: &IRI) -> Result<Vec<Axiom>, Box<dyn error::Error>> {
-    let mut axioms: Vec<Axiom> = Vec::new();
-    let pm_ids = ixn.reference.iter().map(|r| format!("{}/{}", ctd_to_owl_rs::PMID, r.pm_id)).collect_vec();
+/// Bounds how many parsed chunks are queued for or in conversion, so that parsing blocks while the workers are behind instead of holding the rest of the input in memory.
+struct InFlight {
+    release: sync::mpsc::SyncSender<()>,
+    acquire: sync::mpsc::Receiver<()>,
+}
 
-    axioms.push(Axiom::DeclareAnnotationProperty(DeclareAnnotationProperty { 0: build.annotation_property(ctd_to_owl_rs::DC_SOURCE.clone()) }));
+impl InFlight {
+    fn new(limit: usize) -> InFlight {
+        let (release, acquire) = sync::mpsc::sync_channel(limit);
+        (0..limit).for_each(|_| release.send(()).unwrap());
+        InFlight { release, acquire }
+    }
 
-    pm_ids.iter().for_each(|pm_id_iri| {
-        axioms.push(Axiom::AnnotationAssertion(AnnotationAssertion::new(
-            ixn_individual_iri.clone(),
-            Annotation { ap: build.annotation_property(ctd_to_owl_rs::DC_SOURCE.clone()), av: AnnotationValue::IRI(build.iri(pm_id_iri.clone())) },
-        )))
-    });
-    let organism_iri = build.iri(format!("{}-organism", ixn_individual_iri.to_string()));
-    let taxon_iri = build.iri(format!("{}{}", ctd_to_owl_rs::NCBI_TAXON, &taxon.id));
-    axioms.append(&mut build_class_assertion(&taxon_iri.into(), &organism_iri)?);
-    axioms.push(Axiom::AnnotationAssertion(AnnotationAssertion::new(
-        organism_iri.clone(),
-        Annotation {
-            ap: build.annotation_property(horned_owl::vocab::RDFS::Label.iri_s()),
-            av: AnnotationValue::Literal(Literal::Simple { literal: format!("{}#{}", &taxon.text, &ixn.id) }),
-        },
-    )));
-
-    axioms.push(Axiom::DeclareObjectProperty(DeclareObjectProperty { 0: build.object_property(ctd_to_owl_rs::OCCURS_IN.clone()) }));
-    axioms.push(Axiom::DeclareNamedIndividual(DeclareNamedIndividual { 0: organism_iri.clone().into() }));
-    axioms.push(Axiom::ObjectPropertyAssertion(ObjectPropertyAssertion::new(
-        ObjectPropertyExpression::ObjectProperty(build.object_property(ctd_to_owl_rs::OCCURS_IN.clone())),
-        ixn_individual_iri.clone().into(),
-        organism_iri.clone().into(),
-    )));
-    Ok(axioms)
+    /// Waits until fewer than `limit` chunks are in flight; the chunk counts as in flight until the permit is dropped.
+    fn acquire(&self) -> Permit {
+        self.acquire.recv().unwrap();
+        Permit(self.release.clone())
+    }
 }
 
-fn ctd_input_to_model(ctd_input_path: &path::PathBuf) -> Result<Vec<IXN>, Box<dyn error::Error>> {
-    let data = fs::read_to_string(ctd_input_path)?;
-    let ixnset_element = Element::parse(data.as_bytes())?;
-    parse_input(&ixnset_element)
+struct Permit(sync::mpsc::SyncSender<()>);
+
+impl Drop for Permit {
+    fn drop(&mut self) {
+        // cannot fail: the channel has room for every permit and its receiver outlives the scope
+        let _ = self.0.send(());
+    }
 }
 
-fn build_object_property_assertion(object_property: &ObjectProperty, ixn_individual_iri: &IRI, actor_individual: &NamedIndividual) -> Result<Vec<Axiom>, Box<dyn error::Error>> {
-    let mut axioms = Vec::new();
-    axioms.push(Axiom::DeclareObjectProperty(DeclareObjectProperty { 0: object_property.clone() }));
-    axioms.push(Axiom::ObjectPropertyAssertion(ObjectPropertyAssertion::new(object_property.clone().into(), ixn_individual_iri.clone().into(), actor_individual.clone())));
-    Ok(axioms)
+/// Writes every interaction of a chunk as its own named graph; the document header is left to the caller.
+fn write_dataset_chunk<W: io::Write>(
+    write: &mut W,
+    dataset: &rdf::DatasetWriter,
+    converter: &Converter,
+    rejects: &Rejects,
+    model_chunk: Vec<IXN>,
+) -> Result<(), Box<dyn error::Error>> {
+    let build = Build::new();
+    for ixn in model_chunk.iter() {
+        match converter.convert(&build, ixn) {
+            Ok(axioms) => dataset.write_graph(write, &converter.graph_iri(&build, ixn), &format!("ixn{}", ixn.id), &axioms)?,
+            Err(e) => rejects.reject(e),
+        }
+    }
+    Ok(())
 }
 
-fn build_class_assertion(class: &Class, ixn_individual_iri: &IRI) -> Result<Vec<Axiom>, Box<dyn error::Error>> {
-    let mut axioms = Vec::new();
-    axioms.push(Axiom::DeclareClass(DeclareClass { 0: class.clone() }));
-    axioms.push(Axiom::ClassAssertion(ClassAssertion { ce: class.clone().into(), i: ixn_individual_iri.clone().into() }));
-    Ok(axioms)
+fn load_converter(chebi_to_mesh: Option<&path::Path>, mapping_policy: MappingPolicy, mesh_link: MeshLink, config: Option<&path::Path>) -> Result<Converter, Box<dyn error::Error>> {
+    let mapping = match chebi_to_mesh {
+        Some(chebi_to_mesh) => {
+            let (mapping, mapping_report) = ChebiMeshMapping::load(chebi_to_mesh, mapping_policy)?;
+            mapping_report.log(chebi_to_mesh);
+            mapping.with_mesh_link(mesh_link)
+        }
+        None => ChebiMeshMapping::default(),
+    };
+    Ok(match config {
+        Some(config_path) => Converter::with_config(mapping, Config::load(config_path)?)?,
+        None => Converter::new(mapping),
+    })
 }
 
-fn get_actor_from_element(element: &Element) -> Result<Actor, Box<dyn error::Error>> {
-    let actor_type = element.attributes.get("type").unwrap();
-    let actor_id = element.attributes.get("id").unwrap();
-    let actor_position = element.attributes.get("position").unwrap();
-    let actor_parent_id = element.attributes.get("parentid").unwrap();
-    let form = element.attributes.get("form").cloned();
-    //let form_qualifier = element.attributes.get("form_qualifier").cloned();
-    let seq_id = element.attributes.get("seqid").cloned();
-    // let actor =
-    //     Actor::new(actor_type.to_string(), actor_id.to_string(), actor_position.parse::<i32>().unwrap(), actor_parent_id.parse::<i32>().unwrap(), form, form_qualifier, seq_id);
-    let actor = Actor::new(actor_type.to_string(), actor_id.to_string(), actor_position.parse::<i8>().unwrap(), actor_parent_id.parse::<i32>().unwrap(), form, None, seq_id);
-    Ok(actor)
+/// Writes an ontology as one document in a non-dataset format.
+fn write_ontology(output_path: &path::Path, format: Format, ontology: &AxiomMappedOntology, prefix_mapping: &curie::PrefixMapping) -> Result<(), Box<dyn error::Error>> {
+    info!("writing: {:?}", output_path);
+    let mut buf_writer = io::BufWriter::new(fs::File::create(output_path)?);
+    format.write(&mut buf_writer, ontology, prefix_mapping)?;
+    buf_writer.flush()?;
+    Ok(())
 }
 
-fn get_axn_from_element(element: &Element) -> Result<AXN, Box<dyn error::Error>> {
-    let axn_code = element.attributes.get("code").unwrap();
-    let axn_degreecode = element.attributes.get("degreecode").unwrap();
-    let axn_position = element.attributes.get("position").unwrap();
-    let axn_parent_id = element.attributes.get("parentid").unwrap();
-    let axn_text = element.get_text().unwrap().to_string();
-    let axn = AXN::new(axn_code.into(), axn_degreecode.chars().next().unwrap(), axn_position.parse::<i8>().unwrap(), axn_parent_id.parse::<i32>().unwrap(), axn_text);
-    Ok(axn)
+/// Writes the axioms of `ixns` as one document, in named graphs for dataset formats.
+fn write_ixns(
+    output_path: &path::Path,
+    format: Format,
+    converter: &Converter,
+    prefix_mapping: &curie::PrefixMapping,
+    rejects: &Rejects,
+    ixns: Vec<IXN>,
+) -> Result<(), Box<dyn error::Error>> {
+    info!("writing: {:?}", output_path);
+    let mut buf_writer = io::BufWriter::new(fs::File::create(output_path)?);
+    match format.dataset() {
+        Some(dataset_format) => {
+            let dataset = rdf::DatasetWriter::new(dataset_format, prefix_mapping);
+            dataset.write_header(&mut buf_writer, &converter.header_axioms(&Build::new()))?;
+            write_dataset_chunk(&mut buf_writer, &dataset, converter, rejects, ixns)?;
+        }
+        None => {
+            let (ontology, conversion_rejects) = converter.build_ontology(ixns);
+            conversion_rejects.into_iter().for_each(|e| rejects.reject(e));
+            format.write(&mut buf_writer, &ontology, prefix_mapping)?;
+        }
+    }
+    buf_writer.flush()?;
+    Ok(())
 }
 
-fn process_to_process(build: &horned_owl::model::Build, degree: &char) -> horned_owl::model::ObjectProperty {
-    match degree {
-        '1' => build.object_property(ctd_to_owl_rs::CAUSALLY_UPSTREAM_OF.clone()),
-        //"0" => // has no effect? never used in CTD_chem_gene_ixns_structured.xml
-        '+' => build.object_property(ctd_to_owl_rs::CAUSALLY_UPSTREAM_OF_POSITIVE_EFFECT.clone()),
-        '-' => build.object_property(ctd_to_owl_rs::CAUSALLY_UPSTREAM_OF_NEGATIVE_EFFECT.clone()),
-        _ => {
-            panic!("invalid degree")
+fn diff(options: &DiffOptions) -> Result<(), Box<dyn error::Error>> {
+    if options.format == Format::Kgx {
+        return Err("the delta cannot be written as kgx".into());
+    }
+    fs::create_dir_all(&options.output)?;
+    let (old, new) = rayon::join(|| parser::read_ixn_set(&options.old).map_err(|e| e.to_string()), || parser::read_ixn_set(&options.new).map_err(|e| e.to_string()));
+    let diff = diff::IxnDiff::compare(old?, new?);
+    info!("added: {}, removed: {}, changed: {}, unchanged: {}", diff.added.len(), diff.removed.len(), diff.changed.len(), diff.unchanged);
+
+    let output_path = options.output.join("diff.tsv");
+    info!("writing: {:?}", output_path);
+    let mut buf_writer = io::BufWriter::new(fs::File::create(&output_path)?);
+    diff.write_report(&mut buf_writer)?;
+    buf_writer.flush()?;
+
+    if let (true, Some(chebi_to_mesh)) = (options.delta, &options.chebi_to_mesh) {
+        let converter =
+            load_converter(Some(chebi_to_mesh), options.mapping_policy, options.mesh_link, options.config.as_deref())?.with_strict_actor_types(options.strict_actor_types);
+        let prefix_mapping = ctd_to_owl_rs::get_prefix_mapping();
+        let rejects = Rejects::new(options.skip_invalid, None)?;
+        let extension = options.format.extension();
+        write_ixns(&options.output.join(format!("added.{}", extension)), options.format, &converter, &prefix_mapping, &rejects, diff.to_add())?;
+        match options.format.dataset() {
+            Some(_) => write_ixns(&options.output.join(format!("removed.{}", extension)), options.format, &converter, &prefix_mapping, &rejects, diff.to_remove())?,
+            None => write_removed_iris(&options.output.join("removed.tsv"), &converter, diff.to_remove())?,
+        }
+        let skipped = rejects.finish()?;
+        if skipped > 0 {
+            warn!("skipped {} invalid interactions", skipped);
         }
     }
+    Ok(())
 }
 
-fn parse_input(ixnset_element: &Element) -> Result<Vec<IXN>, Box<dyn error::Error>> {
-    let mut ixns = Vec::new();
-
-    for ixn_node in ixnset_element.children.iter() {
-        match ixn_node {
-            XMLNode::Element(ixn_element) => {
-                let ixn_id = ixn_element.attributes.get("id").unwrap();
-                let mut ixn = IXN::new();
-                ixn.id = ixn_id.parse::<i32>().unwrap();
-
-                for ixn_element_child_node in ixn_element.children.iter() {
-                    match ixn_element_child_node {
-                        XMLNode::Element(ixn_child_element) => match ixn_child_element.name.as_str() {
-                            "taxon" => {
-                                let taxon_id = ixn_child_element.attributes.get("id").unwrap();
-                                let taxon_text = ixn_child_element.get_text().unwrap().to_string();
-                                let taxon = Taxon::new(taxon_id.parse::<i32>().unwrap(), taxon_text);
-                                ixn.taxon.push(taxon);
-                                // debug!("{:?}", ixn);
-                            }
-                            "reference" => {
-                                let reference_pm_id = ixn_child_element.attributes.get("pmid").unwrap();
-                                let reference = Reference::new(reference_pm_id.parse::<i32>().unwrap());
-                                ixn.reference.push(reference);
-                                // debug!("{:?}", ixn);
-                            }
-                            "axn" => {
-                                let axn = get_axn_from_element(ixn_child_element).unwrap();
-                                if ixn.id == axn.parent_id {
-                                    ixn.axns.push(axn);
-                                }
-                                // debug!("{:?}", ixn);
-                            }
-                            "actor" => {
-                                parse_actor_element(&mut ixn.actors, &ixn_child_element);
-                            }
-                            _ => {}
-                        },
-                        _ => {}
-                    }
+/// Lists the `CTDIXN` IRI of each interaction, under which all of its individuals are named.
+///
+/// Removed axioms can't be written in a non-dataset format, as a store cannot match their blank nodes on deletion; it drops what it holds about these IRIs instead.
+fn write_removed_iris(output_path: &path::Path, converter: &Converter, ixns: Vec<IXN>) -> Result<(), Box<dyn error::Error>> {
+    info!("writing: {:?}", output_path);
+    let build = Build::new();
+    let mut buf_writer = io::BufWriter::new(fs::File::create(output_path)?);
+    writeln!(buf_writer, "ixn_id\tiri")?;
+    for ixn in ixns.iter() {
+        writeln!(buf_writer, "{}\t{}", ixn.id, converter.graph_iri(&build, ixn))?;
+    }
+    buf_writer.flush()?;
+    Ok(())
+}
+
+/// Writes what the vocabulary of `actor_type` says about the classes of every converted interaction as one document, in the default graph for dataset formats.
+fn write_vocabulary(output_path: &path::Path, format: Format, converter: &Converter, actor_type: &str, prefix_mapping: &curie::PrefixMapping) -> Result<(), Box<dyn error::Error>> {
+    info!("writing: {:?}", output_path);
+    let build = Build::new();
+    let axioms = converter.header_axioms(&build).into_iter().chain(converter.vocabulary_axioms(&build, actor_type)).collect_vec();
+    let mut buf_writer = io::BufWriter::new(fs::File::create(output_path)?);
+    match format.dataset() {
+        Some(dataset_format) => rdf::DatasetWriter::new(dataset_format, prefix_mapping).write_header(&mut buf_writer, &axioms)?,
+        None => {
+            let mut ontology = AxiomMappedOntology::default();
+            axioms.into_iter().for_each(|axiom| {
+                ontology.insert(axiom);
+            });
+            format.write(&mut buf_writer, &ontology, prefix_mapping)?;
+        }
+    }
+    buf_writer.flush()?;
+    Ok(())
+}
+
+/// Writes the parsed, filtered interactions as JSON Lines without converting them.
+fn dump_json(options: &Options, input: &path::Path, output_dir: &path::Path, ixn_filter: &IxnFilter) -> Result<(), Box<dyn error::Error>> {
+    fs::create_dir_all(output_dir)?;
+    let rejects = Rejects::new(options.skip_invalid, options.rejects.as_deref())?;
+    let output_path = output_dir.join("ixns.jsonl");
+    info!("writing: {:?}", output_path);
+    let mut buf_writer = io::BufWriter::new(fs::File::create(&output_path)?);
+    for result in parser::ctd_input_to_model(input)? {
+        match result {
+            Ok(ixn) if ixn_filter.matches(&ixn) => jsonl::write_ixn(&mut buf_writer, &ixn)?,
+            Ok(_) => {}
+            Err(e) if e.is_recoverable() => rejects.reject(e),
+            Err(e) => return Err(e.into()),
+        }
+        if rejects.failed() {
+            break;
+        }
+    }
+    buf_writer.flush()?;
+    let skipped = rejects.finish()?;
+    if skipped > 0 {
+        warn!("skipped {} invalid interactions", skipped);
+    }
+    Ok(())
+}
+
+/// Converts a CTD association table into `<name>_<chunk>.<format>` files, one chunk per parallel task.
+fn convert_table<A: Association + tsv::TsvRecord + Send>(options: &TableOptions, name: &str) -> Result<(), Box<dyn error::Error>> {
+    if options.format.dataset().is_some() || options.format == Format::Kgx {
+        return Err(format!("association tables cannot be written as {}", options.format.extension()).into());
+    }
+    if options.chunk_size == 0 {
+        return Err("--chunk-size must be greater than 0".into());
+    }
+    let converter = load_converter(options.chebi_to_mesh.as_deref(), options.mapping_policy, options.mesh_link, options.config.as_deref())?;
+    let prefix_mapping = ctd_to_owl_rs::get_prefix_mapping();
+    fs::create_dir_all(&options.output)?;
+    let rejects = Rejects::new(options.skip_invalid, options.rejects.as_deref())?;
+    let rows = tsv::TsvReader::<_, A>::new(parser::open_input(&options.input)?);
+    let row_count = atomic::AtomicUsize::new(0);
+    let in_flight = InFlight::new(rayon::current_num_threads());
+    rayon::in_place_scope(|scope| -> Result<(), Box<dyn error::Error>> {
+        for (idx, chunk) in rows.chunks(options.chunk_size).into_iter().enumerate() {
+            let permit = in_flight.acquire();
+            let mut associations: Vec<A> = Vec::new();
+            for result in chunk {
+                match result {
+                    Ok(association) => associations.push(association),
+                    Err(e) if e.is_recoverable() => rejects.reject(e),
+                    Err(e) => return Err(e.into()),
                 }
-                debug!("{:?}", ixn);
-                ixns.push(ixn);
             }
-            _ => {}
+            if rejects.failed() {
+                break;
+            }
+            row_count.fetch_add(associations.len(), atomic::Ordering::Relaxed);
+            let output_path = options.output.join(format!("{}_{}.{}", name, idx, options.format.extension()));
+            let (converter, prefix_mapping, format, rejects) = (&converter, &prefix_mapping, options.format, &rejects);
+            scope.spawn(move |_| {
+                let _permit = permit;
+                let (ontology, conversion_rejects) = AssociationConverter::new(converter).build_ontology(associations);
+                conversion_rejects.into_iter().for_each(|e| rejects.reject(e));
+                if let Err(e) = write_ontology(&output_path, format, &ontology, prefix_mapping) {
+                    rejects.fail(format!("{:?}: {}", output_path, e).into());
+                }
+            });
         }
+        Ok(())
+    })?;
+    let skipped = rejects.finish()?;
+    info!("converted {} rows", row_count.into_inner());
+    if skipped > 0 {
+        warn!("skipped {} invalid rows", skipped);
     }
-    Ok(ixns)
+    Ok(())
 }
 
-fn parse_actor_element(actors: &mut Vec<Actor>, element: &Element) {
-    let mut actor = get_actor_from_element(element).unwrap();
-    match element.get_text() {
-        Some(s) => {
-            actor.text = Some(s.to_string());
-            actors.push(actor);
+fn validate(options: &ValidateOptions) -> Result<(), Box<dyn error::Error>> {
+    let config = match &options.config {
+        Some(config_path) => Config::load(config_path)?,
+        None => Config::default(),
+    };
+    let stdout = io::stdout();
+    let mut out = io::BufWriter::new(stdout.lock());
+    writeln!(out, "line\tixn_id\tmessage")?;
+    let mut violations = 0;
+    let mut write_error = None;
+    let ixn_count = validate::SchemaValidator::new(&config).validate(parser::open_input(&options.input)?, |violation| {
+        violations += 1;
+        if let Err(e) = writeln!(out, "{}\t{}\t{}", violation.line, violation.ixn_id.map(|id| id.to_string()).unwrap_or_default(), violation.message) {
+            write_error.get_or_insert(e);
         }
-        None => {
-            if !element.children.is_empty() {
-                for a_node in element.children.iter() {
-                    match a_node {
-                        XMLNode::Element(a_element) => match a_element.name.as_str() {
-                            "axn" => {
-                                let a_created_axn = get_axn_from_element(a_element).unwrap();
-                                actor.axns.push(a_created_axn.clone());
-                            }
-                            "actor" => parse_actor_element(&mut actor.actors, a_element),
-                            _ => {}
-                        },
-                        _ => {}
+    });
+    if let Some(e) = write_error {
+        return Err(e.into());
+    }
+    out.flush()?;
+    info!("checked {} interactions", ixn_count);
+    if violations > 0 {
+        return Err(format!("{:?}: {} schema violations", options.input, violations).into());
+    }
+    Ok(())
+}
+
+fn main() -> Result<(), Box<dyn error::Error>> {
+    let start = time::Instant::now();
+    env_logger::init();
+    let options = Options::from_args();
+    debug!("{:?}", options);
+    match &options.command {
+        Some(Command::Diff(diff_options)) => diff(diff_options)?,
+        Some(Command::Validate(validate_options)) => validate(validate_options)?,
+        Some(Command::ChemicalDisease(table_options)) => convert_table::<ChemicalDisease>(table_options, "chemical_disease")?,
+        Some(Command::GeneDisease(table_options)) => convert_table::<GeneDisease>(table_options, "gene_disease")?,
+        Some(Command::ChemicalPathway(table_options)) => convert_table::<ChemicalPathway>(table_options, "chemical_pathway")?,
+        Some(Command::GenePathway(table_options)) => convert_table::<GenePathway>(table_options, "gene_pathway")?,
+        None => {}
+    }
+    if options.command.is_some() {
+        info!("Duration: {}", format_duration(start.elapsed()));
+        return Ok(());
+    }
+    let (input, output_dir) = match (options.input.clone(), options.output.clone()) {
+        (Some(input), Some(output_dir)) => (input, output_dir),
+        _ => structopt::clap::Error::with_description("--input and --output are required", structopt::clap::ErrorKind::MissingRequiredArgument).exit(),
+    };
+    let ixn_filter = options.ixn_filter()?;
+    if options.dump_json {
+        dump_json(&options, &input, &output_dir, &ixn_filter)?;
+        info!("Duration: {}", format_duration(start.elapsed()));
+        return Ok(());
+    }
+    let chebi_to_mesh = match options.chebi_to_mesh.clone() {
+        Some(chebi_to_mesh) => chebi_to_mesh,
+        None => structopt::clap::Error::with_description("--chebi-to-mesh is required", structopt::clap::ErrorKind::MissingRequiredArgument).exit(),
+    };
+    let filtered_out = atomic::AtomicUsize::new(0);
+
+    let mut converter =
+        load_converter(Some(&chebi_to_mesh), options.mapping_policy, options.mesh_link, options.config.as_deref())?.with_strict_actor_types(options.strict_actor_types);
+    if let Some(chemical_vocabulary) = options.chemical_vocabulary.as_ref() {
+        if options.format == Format::Kgx {
+            return Err("--chemical-vocabulary cannot be used with kgx".into());
+        }
+        converter = converter.with_chemical_vocabulary(Vocabulary::load(chemical_vocabulary)?);
+    }
+    if let Some(gene_vocabulary) = options.gene_vocabulary.as_ref() {
+        if options.format == Format::Kgx {
+            return Err("--gene-vocabulary cannot be used with kgx".into());
+        }
+        converter = converter.with_gene_vocabulary(Vocabulary::load(gene_vocabulary)?);
+    }
+
+    let prefix_mapping = ctd_to_owl_rs::get_prefix_mapping();
+
+    fs::create_dir_all(&output_dir)?;
+
+    let (format, merge) = (options.format, options.merge);
+    let rejects = Rejects::new(options.skip_invalid, options.rejects.as_deref())?;
+
+    if options.chunk_size == 0 {
+        return Err("--chunk-size must be greater than 0".into());
+    }
+    let merged_chunks = sync::Mutex::new(Vec::new());
+    let merged_datasets = sync::Mutex::new(Vec::new());
+
+    let kgx_exporter = kgx::KgxExporter::new(&converter);
+    let kgx_writer = match format {
+        Format::Kgx => Some(sync::Mutex::new(kgx::KgxWriter::new(io::BufWriter::new(fs::File::create(output_dir.join("edges.tsv"))?))?)),
+        _ => None,
+    };
+
+    let chunks = parser::ctd_input_to_model(&input)?
+        .filter(|result| match result {
+            Ok(ixn) if !ixn_filter.matches(ixn) => {
+                filtered_out.fetch_add(1, atomic::Ordering::Relaxed);
+                false
+            }
+            _ => true,
+        })
+        .chunks(options.chunk_size);
+    let in_flight = InFlight::new(rayon::current_num_threads());
+    rayon::in_place_scope(|scope| -> Result<(), Box<dyn error::Error>> {
+        for (idx, chunk) in chunks.into_iter().enumerate() {
+            let mut model_chunk: Vec<IXN> = Vec::new();
+            for result in chunk {
+                match result {
+                    Ok(ixn) => model_chunk.push(ixn),
+                    Err(e) if e.is_recoverable() => rejects.reject(e),
+                    Err(e) => return Err(e.into()),
+                }
+            }
+            if rejects.failed() {
+                break;
+            }
+            let permit = in_flight.acquire();
+            let (output_dir, prefix_mapping, converter, rejects, format) = (&output_dir, &prefix_mapping, &converter, &rejects, format);
+            if let Some(kgx_writer) = kgx_writer.as_ref() {
+                let kgx_exporter = &kgx_exporter;
+                scope.spawn(move |_| {
+                    let _permit = permit;
+                    let (nodes, edges, conversion_rejects) = kgx_exporter.convert_chunk(&model_chunk);
+                    conversion_rejects.into_iter().for_each(|e| rejects.reject(e));
+                    if let Err(e) = kgx_writer.lock().unwrap().add_chunk(idx, nodes, edges) {
+                        rejects.fail(format!("{:?}: {}", output_dir.join("edges.tsv"), e).into());
+                    }
+                });
+                continue;
+            }
+            if let Some(dataset_format) = format.dataset() {
+                let merged_datasets = &merged_datasets;
+                scope.spawn(move |_| {
+                    let _permit = permit;
+                    let dataset = rdf::DatasetWriter::new(dataset_format, prefix_mapping);
+                    if merge {
+                        let mut body = Vec::new();
+                        match write_dataset_chunk(&mut body, &dataset, converter, rejects, model_chunk) {
+                            Ok(()) => merged_datasets.lock().unwrap().push((idx, body)),
+                            Err(e) => rejects.fail(e),
+                        }
+                        return;
+                    }
+                    let output_path = output_dir.join(format!("{}.{}", idx, format.extension()));
+                    if let Err(e) = write_ixns(&output_path, format, converter, prefix_mapping, rejects, model_chunk) {
+                        rejects.fail(format!("{:?}: {}", output_path, e).into());
                     }
+                });
+                continue;
+            }
+            if merge {
+                let merged_chunks = &merged_chunks;
+                scope.spawn(move |_| {
+                    let _permit = permit;
+                    let build = Build::new();
+                    let mut ontology = AxiomMappedOntology::default();
+                    converter.extend_ontology(&build, &mut ontology, model_chunk).into_iter().for_each(|e| rejects.reject(e));
+                    match format.chunk(&ontology, idx, prefix_mapping) {
+                        Ok(chunk) => merged_chunks.lock().unwrap().push((idx, chunk)),
+                        Err(e) => rejects.fail(e),
+                    }
+                });
+                continue;
+            }
+            scope.spawn(move |_| {
+                let _permit = permit;
+                let output_path = output_dir.join(format!("{}.{}", idx, format.extension()));
+                if let Err(e) = write_ixns(&output_path, format, converter, prefix_mapping, rejects, model_chunk) {
+                    rejects.fail(format!("{:?}: {}", output_path, e).into());
+                }
+            });
+        }
+        Ok(())
+    })?;
+
+    let skipped = rejects.finish()?;
+
+    if let Some(kgx_writer) = kgx_writer {
+        let output_path = output_dir.join("nodes.tsv");
+        info!("writing: {:?}", output_path);
+        kgx_writer.into_inner().unwrap().finish(&mut io::BufWriter::new(fs::File::create(&output_path)?))?;
+    }
+
+    if merge {
+        let output_path = output_dir.join(format!("ctd.{}", format.extension()));
+        info!("writing: {:?}", output_path);
+        let mut buf_writer = io::BufWriter::new(fs::File::create(&output_path)?);
+        match format.dataset() {
+            Some(dataset_format) => {
+                let dataset = rdf::DatasetWriter::new(dataset_format, &prefix_mapping);
+                dataset.write_header(&mut buf_writer, &converter.header_axioms(&Build::new()))?;
+                for (_, body) in merged_datasets.into_inner().unwrap().into_iter().sorted_by_key(|(idx, _)| *idx) {
+                    buf_writer.write_all(&body)?;
                 }
             }
-            actors.push(actor);
+            None => {
+                let mut header = AxiomMappedOntology::default();
+                converter.header_axioms(&Build::new()).into_iter().for_each(|axiom| {
+                    header.insert(axiom);
+                });
+                let chunks = merged_chunks.into_inner().unwrap().into_iter().sorted_by_key(|(idx, _)| *idx).map(|(_, chunk)| chunk).collect_vec();
+                format.write_merged(&mut buf_writer, &header, chunks, &prefix_mapping)?;
+            }
         }
+        buf_writer.flush()?;
+    }
+    if options.chemical_vocabulary.is_some() {
+        write_vocabulary(&output_dir.join(format!("chemical_vocabulary.{}", format.extension())), format, &converter, "chemical", &prefix_mapping)?;
+    }
+    if options.gene_vocabulary.is_some() {
+        write_vocabulary(&output_dir.join(format!("gene_vocabulary.{}", format.extension())), format, &converter, "gene", &prefix_mapping)?;
     }
+    let coverage = converter.take_coverage();
+    let output_path = output_dir.join("coverage.tsv");
+    info!("writing: {:?}", output_path);
+    let mut buf_writer = io::BufWriter::new(fs::File::create(&output_path)?);
+    coverage.write_report(&mut buf_writer)?;
+    buf_writer.flush()?;
+    if coverage.unhandled_count() > 0 {
+        warn!("{} interactions had a shape that was not converted, see {:?}", coverage.unhandled_count(), output_path);
+    }
+    if skipped > 0 {
+        warn!("skipped {} invalid interactions", skipped);
+    }
+    if !ixn_filter.is_empty() {
+        info!("filtered out {} interactions", filtered_out.into_inner());
+    }
+
+    info!("Duration: {}", format_duration(start.elapsed()));
+    Ok(())
 }
@@ -0,0 +1,255 @@
+use crate::actor_type::{ActorType, ActorTypeRegistry};
+use serde::Deserialize;
+use std::collections;
+use std::error;
+use std::path;
+
+/// Object properties used to link the individuals of an interaction and the classes of an association.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct Properties {
+    pub has_input: String,
+    pub has_participant: String,
+    pub part_of: String,
+    pub occurs_in: String,
+    pub causally_upstream_of: String,
+    pub causally_upstream_of_positive_effect: String,
+    pub causally_upstream_of_negative_effect: String,
+    pub gene_product_of: String,
+    /// Curated `marker/mechanism` association with a disease.
+    pub marker_mechanism: String,
+    /// Curated `therapeutic` association with a disease.
+    pub therapeutic: String,
+    /// Association inferred through a shared curated gene or chemical.
+    pub inferred_association: String,
+    /// A gene annotated to a pathway.
+    pub participates_in: String,
+    /// A chemical whose interacting genes are enriched in a pathway.
+    pub enriched_in_pathway: String,
+}
+
+impl Default for Properties {
+    fn default() -> Properties {
+        Properties {
+            has_input: crate::HAS_INPUT.clone(),
+            has_participant: crate::HAS_PARTICIPANT.clone(),
+            part_of: crate::PART_OF.clone(),
+            occurs_in: crate::OCCURS_IN.clone(),
+            causally_upstream_of: crate::CAUSALLY_UPSTREAM_OF.clone(),
+            causally_upstream_of_positive_effect: crate::CAUSALLY_UPSTREAM_OF_POSITIVE_EFFECT.clone(),
+            causally_upstream_of_negative_effect: crate::CAUSALLY_UPSTREAM_OF_NEGATIVE_EFFECT.clone(),
+            gene_product_of: crate::GENE_PRODUCT_OF.clone(),
+            marker_mechanism: crate::IS_MARKER_FOR.clone(),
+            therapeutic: crate::IS_SUBSTANCE_THAT_TREATS.clone(),
+            inferred_association: crate::CORRELATED_WITH.clone(),
+            participates_in: crate::PARTICIPATES_IN.clone(),
+            enriched_in_pathway: crate::ACTS_UPSTREAM_OF_OR_WITHIN.clone(),
+        }
+    }
+}
+
+/// The action code tables and properties the converter maps CTD onto; `Config::default()` holds the built-in values.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Config {
+    /// Class IRI for each action code; `w` and `b` type cotreatment and binding interactions.
+    pub class_map: collections::HashMap<String, String>,
+    /// Codes converted as a simple action of one actor on another.
+    pub axn_codes: Vec<String>,
+    /// Gene product class for gene forms such as `mRNA` or `protein`; other forms keep the gene as the actor's class.
+    pub form_map: collections::HashMap<String, String>,
+    pub properties: Properties,
+    /// How actors of each CTD actor type are turned into classes and labels.
+    pub actor_types: ActorTypeRegistry,
+    /// Namespace of the locally minted annotation properties for CTD fields, such as `has_form`; see `ctd_term`.
+    pub ctd_namespace: String,
+}
+
+impl Default for Config {
+    fn default() -> Config {
+        Config {
+            class_map: crate::get_class_map(),
+            axn_codes: crate::AXN_CODES.iter().map(|c| c.to_string()).collect(),
+            form_map: crate::get_form_map(),
+            properties: Properties::default(),
+            actor_types: ActorTypeRegistry::default(),
+            ctd_namespace: String::from(crate::CTDI),
+        }
+    }
+}
+
+/// The on-disk form, in which every table is optional.
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+struct ConfigFile {
+    #[serde(default)]
+    class_map: collections::HashMap<String, String>,
+    axn_codes: Option<Vec<String>>,
+    #[serde(default)]
+    form_map: collections::HashMap<String, String>,
+    #[serde(default)]
+    properties: Properties,
+    #[serde(default)]
+    actor_types: collections::HashMap<String, ActorType>,
+    ctd_namespace: Option<String>,
+}
+
+impl Config {
+    /// Reads a JSON config; `class_map`, `form_map` and `actor_types` entries are added to or replace the built-in ones, `axn_codes` replaces the built-in list and missing properties keep their defaults.
+    pub fn load(config_path: &path::Path) -> Result<Config, Box<dyn error::Error>> {
+        let config_file: ConfigFile = serde_json::from_reader(crate::parser::open_input(config_path)?).map_err(|e| format!("{:?}: {}", config_path, e))?;
+        let mut config = Config::default();
+        config.class_map.extend(config_file.class_map);
+        config.form_map.extend(config_file.form_map);
+        for (name, actor_type) in config_file.actor_types {
+            config.actor_types.register(&name, actor_type);
+        }
+        if let Some(axn_codes) = config_file.axn_codes {
+            config.axn_codes = axn_codes;
+        }
+        config.properties = config_file.properties;
+        if let Some(ctd_namespace) = config_file.ctd_namespace {
+            config.ctd_namespace = ctd_namespace;
+        }
+        config.validate().map_err(|e| format!("{:?}: {}", config_path, e))?;
+        Ok(config)
+    }
+
+    /// The IRI of a locally minted CTD annotation property, e.g. `direct_evidence`.
+    pub fn ctd_term(&self, name: &str) -> String {
+        format!("{}{}", self.ctd_namespace, name)
+    }
+
+    /// Checks that every code the converter may look up has a class, and that every class, property and namespace is an absolute IRI.
+    pub fn validate(&self) -> Result<(), String> {
+        let mut unmapped_codes = self.axn_codes.iter().map(String::as_str).chain(["w", "b"]).filter(|code| !self.class_map.contains_key(*code)).collect::<Vec<_>>();
+        if !unmapped_codes.is_empty() {
+            unmapped_codes.sort_unstable();
+            unmapped_codes.dedup();
+            return Err(format!("codes with no class_map entry: {}", unmapped_codes.join(", ")));
+        }
+        let p = &self.properties;
+        let properties = [
+            ("has_input", &p.has_input),
+            ("has_participant", &p.has_participant),
+            ("part_of", &p.part_of),
+            ("occurs_in", &p.occurs_in),
+            ("causally_upstream_of", &p.causally_upstream_of),
+            ("causally_upstream_of_positive_effect", &p.causally_upstream_of_positive_effect),
+            ("causally_upstream_of_negative_effect", &p.causally_upstream_of_negative_effect),
+            ("gene_product_of", &p.gene_product_of),
+            ("marker_mechanism", &p.marker_mechanism),
+            ("therapeutic", &p.therapeutic),
+            ("inferred_association", &p.inferred_association),
+            ("participates_in", &p.participates_in),
+            ("enriched_in_pathway", &p.enriched_in_pathway),
+        ];
+        if self.actor_types.get("ixn").is_some() {
+            return Err(String::from("actor type ixn is reserved for nested interactions"));
+        }
+        let actor_type_iris =
+            self.actor_types.iter().flat_map(|(name, t)| std::iter::once((name.as_str(), &t.node_class)).chain(t.id_rewrites.iter().map(move |r| (name.as_str(), &r.namespace))));
+        let mut iris = self
+            .class_map
+            .iter()
+            .chain(self.form_map.iter())
+            .map(|(key, iri)| (key.as_str(), iri))
+            .chain(properties)
+            .chain(actor_type_iris)
+            .chain([("ctd_namespace", &self.ctd_namespace)])
+            .collect::<Vec<_>>();
+        iris.sort();
+        for (name, iri) in iris {
+            if !is_absolute_iri(iri) {
+                return Err(format!("{} is not an absolute IRI: {:?}", name, iri));
+            }
+        }
+        Ok(())
+    }
+}
+
+fn is_absolute_iri(iri: &str) -> bool {
+    match iri.split_once(':') {
+        Some((scheme, rest)) => {
+            !rest.is_empty() && scheme.chars().next().is_some_and(|c| c.is_ascii_alphabetic()) && scheme.chars().all(|c| c.is_ascii_alphanumeric() || "+-.".contains(c))
+        }
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    /// Loads `json` as a config file of its own under the system temp directory.
+    fn load(name: &str, json: &str) -> Result<Config, String> {
+        let path = std::env::temp_dir().join(format!("ctd-to-owl-{}-{}.json", std::process::id(), name));
+        fs::write(&path, json).unwrap();
+        let config = Config::load(&path).map_err(|e| e.to_string());
+        fs::remove_file(&path).unwrap();
+        config
+    }
+
+    #[test]
+    fn default_config_is_valid() {
+        assert_eq!(Config::default().validate(), Ok(()));
+    }
+
+    #[test]
+    fn partial_config_overrides_only_the_keys_it_sets() {
+        let config =
+            load("partial", r#"{"class_map": {"exp": "http://example.org/Expression", "new": "http://example.org/New"}, "properties": {"part_of": "http://example.org/part_of"}}"#)
+                .unwrap();
+        let defaults = Config::default();
+        assert_eq!(config.class_map["exp"], "http://example.org/Expression");
+        assert_eq!(config.class_map["new"], "http://example.org/New");
+        assert_eq!(config.class_map["act"], defaults.class_map["act"]);
+        assert_eq!(config.class_map.len(), defaults.class_map.len() + 1);
+        assert_eq!(config.properties, Properties { part_of: String::from("http://example.org/part_of"), ..Properties::default() });
+        assert_eq!(
+            (&config.axn_codes, &config.form_map, &config.actor_types, &config.ctd_namespace),
+            (&defaults.axn_codes, &defaults.form_map, &defaults.actor_types, &defaults.ctd_namespace)
+        );
+    }
+
+    #[test]
+    fn axn_codes_replace_the_built_in_list() {
+        let config = load("axn-codes", r#"{"axn_codes": ["exp", "act"]}"#).unwrap();
+        assert_eq!(config.axn_codes, vec!["exp", "act"]);
+    }
+
+    #[test]
+    fn actor_types_are_added_to_the_built_in_ones() {
+        let config = load(
+            "actor-types",
+            r#"{"actor_types": {"disease": {"id_rewrites": [{"prefix": "MESH:", "namespace": "http://id.nlm.nih.gov/mesh/"}], "node_class": "http://example.org/Disease", "label": "text"}}}"#,
+        )
+        .unwrap();
+        assert_eq!(config.actor_types.get("disease").unwrap().class_iri("MESH:D1"), "http://id.nlm.nih.gov/mesh/D1");
+        assert!(config.actor_types.get("chemical").is_some() && config.actor_types.get("gene").is_some());
+    }
+
+    #[test]
+    fn unknown_axn_codes_are_rejected() {
+        let error = load("unknown-codes", r#"{"axn_codes": ["exp", "zzz", "yyy", "zzz"]}"#).unwrap_err();
+        assert!(error.ends_with("codes with no class_map entry: yyy, zzz"), "{}", error);
+    }
+
+    #[test]
+    fn relative_iris_are_rejected() {
+        let error = load("relative-class", r#"{"class_map": {"exp": "GO_0010467"}}"#).unwrap_err();
+        assert!(error.ends_with("exp is not an absolute IRI: \"GO_0010467\""), "{}", error);
+        let error = load("relative-property", r#"{"properties": {"has_input": "RO:"}}"#).unwrap_err();
+        assert!(error.ends_with("has_input is not an absolute IRI: \"RO:\""), "{}", error);
+        let error = load("relative-namespace", r#"{"ctd_namespace": "ctd/"}"#).unwrap_err();
+        assert!(error.ends_with("ctd_namespace is not an absolute IRI: \"ctd/\""), "{}", error);
+    }
+
+    #[test]
+    fn unknown_keys_and_the_ixn_actor_type_are_rejected() {
+        assert!(load("unknown-key", r#"{"class_maps": {}}"#).unwrap_err().contains("unknown field `class_maps`"));
+        assert!(load("unknown-property", r#"{"properties": {"has_output": "http://example.org/has_output"}}"#).unwrap_err().contains("unknown field `has_output`"));
+        let error = load("ixn-type", r#"{"actor_types": {"ixn": {"id_rewrites": [], "node_class": "http://example.org/Ixn", "label": "text"}}}"#).unwrap_err();
+        assert!(error.ends_with("actor type ixn is reserved for nested interactions"), "{}", error);
+    }
+}
@@ -0,0 +1,730 @@
+use crate::actor_type::ActorType;
+use crate::config::{Config, Properties};
+use crate::coverage::{Branch, Coverage};
+use crate::error::CtdError;
+use crate::mapping::{ChebiMeshMapping, MeshLink, Resolution};
+use crate::model::*;
+use crate::vocabulary::{Vocabulary, VocabularyTerm};
+use horned_owl::model::*;
+use horned_owl::ontology;
+use horned_owl::vocab::WithIRI;
+use itertools::Itertools;
+use std::collections;
+use std::error;
+use std::sync;
+
+/// The class IRI, node type IRI, Biolink category and display text of an atomic actor.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ResolvedActor {
+    pub class_iri: String,
+    /// Classes `class_iri` is equivalent to, when an ambiguous MESH id is kept under `MappingPolicy::All`.
+    pub equivalent_class_iris: Vec<String>,
+    /// The `MESH:` id a CHEBI `class_iri` was mapped from.
+    pub mapped_from: Option<String>,
+    pub node_type_iri: String,
+    pub category: String,
+    pub text: String,
+}
+
+/// Converts parsed CTD interactions into OWL axioms, resolving chemicals through a MESH to CHEBI mapping table.
+pub struct Converter {
+    mapping: ChebiMeshMapping,
+    config: Config,
+    coverage: sync::Mutex<Coverage>,
+    chemical_vocabulary: Option<Vocabulary<ChemicalTerm>>,
+    gene_vocabulary: Option<Vocabulary<GeneTerm>>,
+    /// Actor ids converted so far by actor type, kept only for the types a vocabulary will describe.
+    referenced_ids: sync::Mutex<collections::BTreeMap<&'static str, collections::BTreeSet<String>>>,
+    /// Whether an interaction with an unregistered actor type fails instead of being skipped.
+    strict_actor_types: bool,
+}
+
+impl Converter {
+    /// `mapping` is a loaded `ChebiMeshMapping`, or a map keyed by `MESH:` id with the `CHEBI:` id to use in its place as value.
+    pub fn new<M: Into<ChebiMeshMapping>>(mapping: M) -> Converter {
+        Converter {
+            mapping: mapping.into(),
+            config: Config::default(),
+            coverage: sync::Mutex::new(Coverage::default()),
+            chemical_vocabulary: None,
+            gene_vocabulary: None,
+            referenced_ids: sync::Mutex::new(collections::BTreeMap::new()),
+            strict_actor_types: false,
+        }
+    }
+
+    /// Same as `new`, with action code tables and properties from `config` instead of the built-in ones.
+    pub fn with_config<M: Into<ChebiMeshMapping>>(mapping: M, config: Config) -> Result<Converter, Box<dyn error::Error>> {
+        config.validate()?;
+        Ok(Converter {
+            mapping: mapping.into(),
+            config,
+            coverage: sync::Mutex::new(Coverage::default()),
+            chemical_vocabulary: None,
+            gene_vocabulary: None,
+            referenced_ids: sync::Mutex::new(collections::BTreeMap::new()),
+            strict_actor_types: false,
+        })
+    }
+
+    /// Labels chemical classes from CTD's chemical vocabulary instead of the text of each interaction; see `vocabulary_axioms`.
+    pub fn with_chemical_vocabulary(self, chemical_vocabulary: Vocabulary<ChemicalTerm>) -> Converter {
+        Converter { chemical_vocabulary: Some(chemical_vocabulary), ..self }
+    }
+
+    /// Labels genes from CTD's gene vocabulary instead of the text or seqid of each interaction; see `vocabulary_axioms`.
+    pub fn with_gene_vocabulary(self, gene_vocabulary: Vocabulary<GeneTerm>) -> Converter {
+        Converter { gene_vocabulary: Some(gene_vocabulary), ..self }
+    }
+
+    /// Fails interactions with an unregistered actor type with `CtdError::UnknownActorType` instead of skipping them.
+    pub fn with_strict_actor_types(self, strict_actor_types: bool) -> Converter {
+        Converter { strict_actor_types, ..self }
+    }
+
+    /// Labels and annotations from the vocabulary of `actor_type`, `chemical` or `gene`, for every one of its ids converted so far, on the classes they resolve to; chemicals also get the MeSH hierarchy.
+    pub fn vocabulary_axioms(&self, build: &Build, actor_type: &str) -> Vec<Axiom> {
+        let registered = match self.config.actor_types.get(actor_type) {
+            Some(registered) => registered,
+            None => return Vec::new(),
+        };
+        let referenced_ids = self.referenced_ids.lock().unwrap();
+        let ids = referenced_ids.get(actor_type).into_iter().flatten().map(String::as_str);
+        let class_iri = |id: &str| self.resolve_id(registered, id, String::new()).class_iri;
+        match actor_type {
+            ChemicalTerm::ACTOR_TYPE => self.chemical_vocabulary.as_ref().map(|v| v.axioms(build, &self.config, class_iri, ids)).unwrap_or_default(),
+            GeneTerm::ACTOR_TYPE => self.gene_vocabulary.as_ref().map(|v| v.axioms(build, &self.config, class_iri, ids)).unwrap_or_default(),
+            _ => Vec::new(),
+        }
+    }
+
+    /// The actor types a vocabulary was given for.
+    fn vocabulary_types(&self) -> Vec<&'static str> {
+        let mut actor_types = Vec::new();
+        if self.chemical_vocabulary.is_some() {
+            actor_types.push(ChemicalTerm::ACTOR_TYPE);
+        }
+        if self.gene_vocabulary.is_some() {
+            actor_types.push(GeneTerm::ACTOR_TYPE);
+        }
+        actor_types
+    }
+
+    /// The label a vocabulary gives an actor, used in place of its text.
+    fn vocabulary_label(&self, actor: &Actor) -> Option<&str> {
+        match actor.actor_type.as_str() {
+            ChemicalTerm::ACTOR_TYPE => self.chemical_vocabulary.as_ref()?.get(&actor.id).map(VocabularyTerm::label),
+            GeneTerm::ACTOR_TYPE => self.gene_vocabulary.as_ref()?.get(&actor.id).map(VocabularyTerm::label),
+            _ => None,
+        }
+    }
+
+    /// Coverage of everything converted so far, leaving it empty for the next run.
+    pub fn take_coverage(&self) -> Coverage {
+        std::mem::take(&mut *self.coverage.lock().unwrap())
+    }
+
+    /// The `CTDIXN` IRI of an interaction, which also names its graph.
+    pub fn graph_iri(&self, build: &Build, ixn: &IXN) -> IRI {
+        build.iri(format!("{}{}", crate::CTDIXN, ixn.id))
+    }
+
+    /// The ontology header shared by every output document.
+    pub fn header_axioms(&self, build: &Build) -> Vec<Axiom> {
+        let provided_by_ap = build.annotation_property("http://purl.org/pav/providedBy");
+        let ontology_root_iri = build.iri("http://ctdbase.org");
+        vec![
+            Axiom::DeclareAnnotationProperty(DeclareAnnotationProperty(provided_by_ap.clone())),
+            Axiom::OntologyAnnotation(OntologyAnnotation(Annotation { ap: provided_by_ap, av: AnnotationValue::IRI(ontology_root_iri) })),
+        ]
+    }
+
+    /// Converts a single interaction into axioms, one local interaction individual per taxon.
+    ///
+    /// An interaction with an unregistered actor type yields no axioms; see `check_actor_types`.
+    pub fn convert(&self, build: &Build, ixn: &IXN) -> Result<Vec<Axiom>, CtdError> {
+        if !self.check_actor_types(ixn)? {
+            return Ok(Vec::new());
+        }
+        let mut axioms: Vec<Axiom> = Vec::new();
+        let provided_by_ap = build.annotation_property("http://purl.org/pav/providedBy");
+        let ontology_root_iri = build.iri("http://ctdbase.org");
+        let graph_iri = self.graph_iri(build, ixn);
+        axioms.push(Axiom::AnnotationAssertion(AnnotationAssertion::new(graph_iri.clone(), Annotation { ap: provided_by_ap, av: AnnotationValue::IRI(ontology_root_iri) })));
+
+        for (taxon_idx, taxon) in ixn.taxon.iter().enumerate() {
+            let ixn_individual_iri = build.iri(format!("{}#{}", graph_iri, taxon_idx));
+            debug!("ixn_individual_iri: {:?}", ixn_individual_iri);
+            axioms.push(Axiom::DeclareNamedIndividual(DeclareNamedIndividual(ixn_individual_iri.clone().into())));
+
+            match self.process_actor(build, ixn, &taxon_idx, taxon, &ixn_individual_iri, &ixn.axns, &ixn.actors)? {
+                Some((_, mut actor_axioms)) => {
+                    debug!("using ixn: {}", ixn.id);
+                    axioms.append(&mut actor_axioms);
+                }
+                _ => {
+                    debug!("skipping ixn: {}", ixn.id);
+                }
+            }
+        }
+        let vocabulary_types = self.vocabulary_types();
+        if !vocabulary_types.is_empty() {
+            let mut referenced_ids = self.referenced_ids.lock().unwrap();
+            for actor_type in vocabulary_types {
+                let ids = referenced_ids.entry(actor_type).or_default();
+                ixn.actors.iter().flat_map(|a| a.flat()).filter(|a| a.actor_type == actor_type).for_each(|a| {
+                    ids.insert(a.id.clone());
+                });
+            }
+        }
+        self.record_coverage(ixn);
+        Ok(axioms)
+    }
+
+    /// Whether every actor type in an interaction is registered; unregistered ones are counted in the coverage, and are an error with `with_strict_actor_types`.
+    pub fn check_actor_types(&self, ixn: &IXN) -> Result<bool, CtdError> {
+        let unknown_types = self.config.actor_types.unknown_types(ixn);
+        let actor_type = match unknown_types.first() {
+            Some(actor_type) => actor_type,
+            None => return Ok(true),
+        };
+        let mut coverage = self.coverage.lock().unwrap();
+        unknown_types.iter().for_each(|t| coverage.record_unknown_actor_type(t, ixn.id));
+        if self.strict_actor_types {
+            return Err(CtdError::UnknownActorType { ixn_id: ixn.id, actor_type: actor_type.to_string() });
+        }
+        warn!("ixn {}: skipping, unknown actor type {:?}", ixn.id, actor_type);
+        Ok(false)
+    }
+
+    /// Records an interaction's branch, unhandled shapes and codes, and each chemical in it that falls back to MESH, once per interaction.
+    pub fn record_coverage(&self, ixn: &IXN) {
+        let mesh_fallbacks = ixn
+            .actors
+            .iter()
+            .flat_map(|a| a.flat())
+            .filter(|actor| self.config.actor_types.get(actor.actor_type.as_str()).is_some_and(|t| t.chebi_mapping))
+            .filter(|actor| self.mapping.resolve(&actor.id) == Resolution::Unmapped)
+            .map(|actor| actor.id.as_str())
+            .unique()
+            .collect_vec();
+        let mut coverage = self.coverage.lock().unwrap();
+        coverage.record_ixn(ixn, &self.config);
+        mesh_fallbacks.into_iter().for_each(|mesh_id| coverage.record_mesh_fallback(mesh_id));
+    }
+
+    /// Converts a batch of interactions into a single ontology, annotated as provided by CTD.
+    ///
+    /// Interactions that fail to convert are left out of the ontology and returned alongside it.
+    pub fn build_ontology<I: IntoIterator<Item = IXN>>(&self, model: I) -> (ontology::axiom_mapped::AxiomMappedOntology, Vec<CtdError>) {
+        let build = horned_owl::model::Build::new();
+        let mut ontology = ontology::axiom_mapped::AxiomMappedOntology::default();
+        self.header_axioms(&build).into_iter().for_each(|axiom| {
+            ontology.insert(axiom);
+        });
+        let rejects = self.extend_ontology(&build, &mut ontology, model);
+        (ontology, rejects)
+    }
+
+    /// Converts a batch of interactions into an existing ontology, such as one merging several batches, returning those that fail to convert.
+    pub fn extend_ontology<I: IntoIterator<Item = IXN>>(&self, build: &Build, ontology: &mut ontology::axiom_mapped::AxiomMappedOntology, model: I) -> Vec<CtdError> {
+        let mut rejects = Vec::new();
+        for ixn in model {
+            match self.convert(build, &ixn) {
+                Ok(axioms) => axioms.into_iter().for_each(|axiom| {
+                    ontology.insert(axiom);
+                }),
+                Err(e) => rejects.push(e),
+            }
+        }
+        rejects
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn process_actor(
+        &self,
+        build: &Build,
+        ixn: &IXN,
+        taxon_idx: &usize,
+        taxon: &Taxon,
+        ixn_individual_iri: &IRI,
+        axns: &[AXN],
+        actors: &[Actor],
+    ) -> Result<Option<(NamedIndividual, Vec<Axiom>)>, CtdError> {
+        let codes = axns.iter().map(|a| a.code.clone()).collect_vec();
+        let branch = Branch::classify(&self.config, axns, actors);
+
+        if branch == Some(Branch::Cotreatment) {
+            // cotreatment
+            let mut axioms: Vec<Axiom> = Vec::new();
+            for actor in actors.iter() {
+                let (actor_individual, mut atomic_actor_axioms) = self.get_local_individual_and_axioms(build, ixn, actor, taxon_idx)?;
+                axioms.append(&mut atomic_actor_axioms);
+                axioms.append(&mut build_object_property_assertion(&build.object_property(self.config.properties.has_input.clone()), ixn_individual_iri, &actor_individual));
+                axioms.append(&mut build_class_assertion(&build.class(self.config.class_map["w"].clone()), ixn_individual_iri));
+                let mut remnant_axioms = add_remnants(build, &self.config.properties, ixn, taxon, ixn_individual_iri);
+                axioms.append(&mut remnant_axioms);
+            }
+            return Ok(Some((ixn_individual_iri.clone().into(), axioms)));
+        }
+
+        if branch == Some(Branch::Binding) {
+            // binding
+            let mut axioms: Vec<Axiom> = Vec::new();
+            for actor in actors.iter() {
+                let (actor_individual, mut atomic_actor_axioms) = self.get_local_individual_and_axioms(build, ixn, actor, taxon_idx)?;
+                axioms.append(&mut atomic_actor_axioms);
+                axioms.append(&mut build_object_property_assertion(&build.object_property(self.config.properties.has_input.clone()), ixn_individual_iri, &actor_individual));
+                axioms.append(&mut build_class_assertion(&build.class(self.config.class_map["b"].clone()), ixn_individual_iri));
+                let mut remnant_axioms = add_remnants(build, &self.config.properties, ixn, taxon, ixn_individual_iri);
+                axioms.append(&mut remnant_axioms);
+            }
+            return Ok(Some((ixn_individual_iri.clone().into(), axioms)));
+        }
+
+        if branch == Some(Branch::Rxn) {
+            let mut axioms: Vec<Axiom> = Vec::new();
+
+            let subject = &actors[0];
+            let results = match subject.actor_type.as_str() {
+                "ixn" => self.process_actor(build, ixn, taxon_idx, taxon, ixn_individual_iri, &subject.axns, &subject.actors)?,
+                _ => Some(self.get_local_individual_and_axioms(build, ixn, subject, taxon_idx)?),
+            };
+
+            if let Some((subject_individual, subject_axioms)) = results {
+                let target = &actors[1];
+                match self.process_actor(build, ixn, taxon_idx, taxon, ixn_individual_iri, &target.axns, &target.actors)? {
+                    Some((target_individual, mut target_axioms)) => {
+                        debug!("rxn - target.id: {:?}", target.id);
+
+                        axioms.append(&mut target_axioms);
+                        axioms.append(&mut subject_axioms.clone());
+                        let subject_process_iri = build.iri(format!("{}-process", subject_individual.0));
+                        axioms.push(Axiom::DeclareNamedIndividual(DeclareNamedIndividual(subject_process_iri.clone().into())));
+
+                        let process_class = build.class(crate::PROCESS.clone());
+                        axioms.push(Axiom::DeclareClass(DeclareClass(process_class.clone())));
+                        axioms.push(Axiom::ClassAssertion(ClassAssertion { ce: process_class.clone().into(), i: ixn_individual_iri.clone().into() }));
+
+                        let part_of_class = build.object_property(self.config.properties.part_of.clone());
+                        axioms.push(Axiom::DeclareObjectProperty(DeclareObjectProperty(part_of_class.clone())));
+                        axioms.push(Axiom::ObjectPropertyAssertion(ObjectPropertyAssertion::new(
+                            part_of_class.into(),
+                            target_individual.clone(),
+                            ixn_individual_iri.clone().into(),
+                        )));
+
+                        let axn = axns.first().ok_or(CtdError::MissingAxn { ixn_id: ixn.id })?;
+                        let process_to_process_op = process_to_process(build, &self.config.properties, ixn, axn.degree());
+                        axioms.append(&mut build_process_link(&process_to_process_op, &subject_process_iri, &target_individual));
+                    }
+                    None => {
+                        debug!("failed to process actor: {:?}", target)
+                    }
+                };
+            }
+
+            return Ok(Some((ixn_individual_iri.clone().into(), axioms)));
+        }
+
+        if branch == Some(Branch::SimpleAxn) {
+            let mut axioms: Vec<Axiom> = Vec::new();
+
+            let subject = &actors[0];
+            let results = match subject.actor_type.as_str() {
+                "ixn" => self.process_actor(build, ixn, taxon_idx, taxon, ixn_individual_iri, &subject.axns, &subject.actors)?,
+                _ => Some(self.get_local_individual_and_axioms(build, ixn, subject, taxon_idx)?),
+            };
+
+            if let Some((subject_individual, subject_axioms)) = results {
+                let subject_process = build.named_individual(format!("{}-process", subject_individual.0));
+                axioms.push(Axiom::DeclareNamedIndividual(DeclareNamedIndividual(subject_individual.clone())));
+                axioms.push(Axiom::DeclareNamedIndividual(DeclareNamedIndividual(subject_process.clone())));
+
+                axioms.append(&mut build_class_assertion(&build.class(crate::PROCESS.clone()), &subject_process.0));
+                axioms.append(&mut build_object_property_assertion(
+                    &build.object_property(self.config.properties.has_participant.clone()),
+                    &subject_process.0,
+                    &subject_individual,
+                ));
+
+                axioms.append(&mut subject_axioms.clone());
+                let axn = axns.first().ok_or(CtdError::MissingAxn { ixn_id: ixn.id })?;
+                let process_to_process_op = process_to_process(build, &self.config.properties, ixn, axn.degree());
+
+                let target = &actors[1];
+                let (target_individual, mut target_axioms) = self.get_local_individual_and_axioms(build, ixn, target, taxon_idx)?;
+                axioms.append(&mut target_axioms);
+                codes.iter().filter_map(|code| self.config.class_map.get(code)).enumerate().for_each(|(idx, ixn_type)| {
+                    let ixn_type_class = build.class(ixn_type.clone());
+                    let local_ixn_iri = build.iri(format!("{}{}#{}-target-{}", crate::CTDIXN, ixn.id, taxon_idx, idx));
+
+                    axioms.append(&mut build_class_assertion(&ixn_type_class, &local_ixn_iri));
+
+                    axioms.push(Axiom::DeclareNamedIndividual(DeclareNamedIndividual(local_ixn_iri.clone().into())));
+
+                    axioms.push(Axiom::ObjectPropertyAssertion(ObjectPropertyAssertion::new(
+                        build.object_property(self.config.properties.has_participant.clone()).into(),
+                        local_ixn_iri.clone().into(),
+                        target_individual.clone(),
+                    )));
+
+                    axioms.append(&mut build_process_link(&process_to_process_op, &subject_process.0, &local_ixn_iri.clone().into()));
+
+                    let part_of_op = build.object_property(self.config.properties.part_of.clone());
+                    axioms.push(Axiom::DeclareObjectProperty(DeclareObjectProperty(part_of_op.clone())));
+                    axioms.push(Axiom::ObjectPropertyAssertion(ObjectPropertyAssertion::new(part_of_op.into(), local_ixn_iri.clone().into(), ixn_individual_iri.clone().into())));
+                });
+                let mut remnant_axioms = add_remnants(build, &self.config.properties, ixn, taxon, ixn_individual_iri);
+                axioms.append(&mut remnant_axioms);
+            }
+            return Ok(Some((ixn_individual_iri.clone().into(), axioms)));
+        }
+
+        debug!("not using ixn: {:?}", ixn.id);
+        Ok(None)
+    }
+
+    /// Resolves an atomic actor to the class it stands for through its registered actor type; chemicals use CHEBI where mapped.
+    ///
+    /// The text is the vocabulary's label where the actor is in one, whatever the interaction spells it as.
+    pub fn resolve_actor(&self, ixn: &IXN, actor: &Actor) -> Result<ResolvedActor, CtdError> {
+        match self.config.actor_types.get(actor.actor_type.as_str()) {
+            Some(actor_type) => {
+                let text = match self.vocabulary_label(actor) {
+                    Some(label) => label.to_string(),
+                    None => actor_type.label(actor),
+                };
+                Ok(self.resolve_id(actor_type, &actor.id, text))
+            }
+            None => Err(CtdError::UnknownActorType { ixn_id: ixn.id, actor_type: actor.actor_type.clone() }),
+        }
+    }
+
+    /// Same as `resolve_actor` for an id met outside of an interaction, e.g. in an association table, labelled with `text`.
+    pub fn resolve_id(&self, actor_type: &ActorType, id: &str, text: String) -> ResolvedActor {
+        let resolution = match actor_type.chebi_mapping {
+            true => self.mapping.resolve(id),
+            false => Resolution::Unmapped,
+        };
+        let (class_iri, equivalent_class_iris, mapped_from) = match resolution {
+            Resolution::Chebi(chebi) => (chebi.replace("CHEBI:", crate::CHEBI), Vec::new(), Some(id.to_string())),
+            Resolution::Equivalents(chebis) => (actor_type.class_iri(id), chebis.iter().map(|chebi| chebi.replace("CHEBI:", crate::CHEBI)).collect(), None),
+            Resolution::Unmapped => (actor_type.class_iri(id), Vec::new(), None),
+        };
+        ResolvedActor { class_iri, equivalent_class_iris, mapped_from, node_type_iri: actor_type.node_class.clone(), category: actor_type.kgx_category.clone(), text }
+    }
+
+    /// The action code tables, properties and actor types in use.
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    /// Links a resolved class to the CHEBI classes it is equivalent to and, under a `MeshLink`, to the MESH class it was mapped from.
+    pub fn class_link_axioms(&self, build: &Build, actor_class: &Class, equivalent_class_iris: &[String], mapped_from: Option<&str>) -> Vec<Axiom> {
+        let mut axioms: Vec<Axiom> = Vec::new();
+
+        // actorClass EquivalentTo chebiClass, for each CHEBI class of an ambiguous MESH id
+        for equivalent_class_iri in equivalent_class_iris.iter() {
+            let equivalent_class = build.class(equivalent_class_iri.clone());
+            axioms.push(Axiom::DeclareClass(DeclareClass(equivalent_class.clone())));
+            axioms.push(Axiom::EquivalentClasses(EquivalentClasses(vec![actor_class.clone().into(), equivalent_class.into()])));
+        }
+
+        // meshClass EquivalentTo actorClass, or actorClass Annotation(exactMatch, meshClass) / Annotation(hasDbXref, "MESH:...")
+        if let Some(mesh_id) = mapped_from {
+            let mesh_iri = mesh_id.replace("MESH:", crate::MESH);
+            match self.mapping.mesh_link() {
+                MeshLink::None => {}
+                MeshLink::Equivalent => {
+                    let mesh_class = build.class(mesh_iri);
+                    axioms.push(Axiom::DeclareClass(DeclareClass(mesh_class.clone())));
+                    axioms.push(Axiom::EquivalentClasses(EquivalentClasses(vec![mesh_class.into(), actor_class.clone().into()])));
+                }
+                MeshLink::ExactMatch => {
+                    axioms.append(&mut build_annotation_property(build, &crate::SKOS_EXACT_MATCH, "exact match"));
+                    axioms.push(Axiom::AnnotationAssertion(AnnotationAssertion::new(
+                        actor_class.0.clone(),
+                        Annotation { ap: build.annotation_property(crate::SKOS_EXACT_MATCH.as_str()), av: AnnotationValue::IRI(build.iri(mesh_iri)) },
+                    )));
+                }
+                MeshLink::Xref => {
+                    axioms.append(&mut build_annotation_property(build, &crate::HAS_DB_XREF, "database_cross_reference"));
+                    axioms.push(Axiom::AnnotationAssertion(AnnotationAssertion::new(
+                        actor_class.0.clone(),
+                        Annotation { ap: build.annotation_property(crate::HAS_DB_XREF.as_str()), av: AnnotationValue::Literal(Literal::Simple { literal: mesh_id.to_string() }) },
+                    )));
+                }
+            }
+        }
+        axioms
+    }
+
+    fn get_local_individual_and_axioms(&self, build: &Build, ixn: &IXN, actor: &Actor, taxon_idx: &usize) -> Result<(NamedIndividual, Vec<Axiom>), CtdError> {
+        let resolved = self.resolve_actor(ixn, actor)?;
+        let actor_class = build.class(resolved.class_iri.clone());
+        let actor_entity = build.class(resolved.node_type_iri.clone());
+        let actor_text = resolved.text.clone();
+        let actor_label = format!("{}#{}-{}", actor_text, actor.parent_id, actor.position);
+
+        let mut axioms: Vec<Axiom> = Vec::new();
+
+        let actor_individual_iri = build.iri(format!("{}{}#{}-{}", crate::CTDIXN, actor.parent_id, taxon_idx, actor.position));
+        axioms.push(Axiom::DeclareNamedIndividual(DeclareNamedIndividual(actor_individual_iri.clone().into())));
+
+        let gene_product_class = match actor.actor_type.as_str() {
+            "gene" => actor.form.as_ref().and_then(|form| self.config.form_map.get(form)),
+            _ => None,
+        };
+        match gene_product_class {
+            Some(gene_product_class) => {
+                // a gene given as mRNA or protein is a product of the gene, not the gene itself
+                // actorInd Type productClass, actorInd Type (geneProductOf some actorClass)
+                axioms.append(&mut build_class_assertion(&build.class(gene_product_class.clone()), &actor_individual_iri));
+                let gene_product_of = build.object_property(self.config.properties.gene_product_of.clone());
+                axioms.push(Axiom::DeclareObjectProperty(DeclareObjectProperty(gene_product_of.clone())));
+                axioms.push(Axiom::DeclareClass(DeclareClass(actor_class.clone())));
+                axioms.push(Axiom::ClassAssertion(ClassAssertion {
+                    ce: ClassExpression::ObjectSomeValuesFrom { ope: gene_product_of.into(), bce: Box::new(actor_class.clone().into()) },
+                    i: actor_individual_iri.clone().into(),
+                }));
+            }
+            None => {
+                // actorInd Type actorClass, actorInd Type nodeType
+                axioms.append(&mut build_class_assertion(&actor_class, &actor_individual_iri));
+                axioms.append(&mut build_class_assertion(&actor_entity, &actor_individual_iri));
+            }
+        }
+
+        // actorClass Annotation(RDFSLabel, typeLabel), unless the vocabulary output labels it
+        if self.vocabulary_label(actor).is_none() {
+            axioms.push(Axiom::AnnotationAssertion(AnnotationAssertion::new(
+                actor_class.0.clone(),
+                Annotation { ap: build.annotation_property(horned_owl::vocab::RDFS::Label.iri_s()), av: AnnotationValue::Literal(Literal::Simple { literal: actor_text.clone() }) },
+            )));
+        }
+
+        axioms.append(&mut self.class_link_axioms(build, &actor_class, &resolved.equivalent_class_iris, resolved.mapped_from.as_deref()));
+
+        // actorInd Annotation(RDFSLabel, label)
+        axioms.push(Axiom::AnnotationAssertion(AnnotationAssertion::new(
+            actor_individual_iri.clone(),
+            Annotation { ap: build.annotation_property(horned_owl::vocab::RDFS::Label.iri_s()), av: AnnotationValue::Literal(Literal::Simple { literal: actor_label }) },
+        )));
+
+        // the form as given by CTD is kept even when it was mapped to a class
+        for (value, term, property_label) in [(&actor.form, "has_form", "has form"), (&actor.form_qualifier, "has_form_qualifier", "has form qualifier")] {
+            if let Some(s) = value {
+                let property = self.config.ctd_term(term);
+                axioms.append(&mut build_annotation_property(build, &property, property_label));
+                axioms.push(Axiom::AnnotationAssertion(AnnotationAssertion::new(
+                    actor_individual_iri.clone(),
+                    Annotation { ap: build.annotation_property(property), av: AnnotationValue::Literal(Literal::Simple { literal: s.to_string() }) },
+                )));
+            }
+        }
+        Ok((actor_individual_iri.into(), axioms))
+    }
+}
+
+fn add_remnants(build: &Build, properties: &Properties, ixn: &IXN, taxon: &Taxon, ixn_individual_iri: &IRI) -> Vec<Axiom> {
+    let mut axioms: Vec<Axiom> = Vec::new();
+    let pm_ids = ixn.reference.iter().map(|r| pm_id_iri(r.pm_id)).collect_vec();
+
+    axioms.push(Axiom::DeclareAnnotationProperty(DeclareAnnotationProperty(build.annotation_property(crate::DC_SOURCE.clone()))));
+
+    pm_ids.iter().for_each(|pm_id_iri| {
+        axioms.push(Axiom::AnnotationAssertion(AnnotationAssertion::new(
+            ixn_individual_iri.clone(),
+            Annotation { ap: build.annotation_property(crate::DC_SOURCE.clone()), av: AnnotationValue::IRI(build.iri(pm_id_iri.clone())) },
+        )))
+    });
+    let organism_iri = build.iri(format!("{}-organism", ixn_individual_iri));
+    let taxon_iri = build.iri(format!("{}{}", crate::NCBI_TAXON, &taxon.id));
+    axioms.append(&mut build_class_assertion(&taxon_iri.into(), &organism_iri));
+    axioms.push(Axiom::AnnotationAssertion(AnnotationAssertion::new(
+        organism_iri.clone(),
+        Annotation {
+            ap: build.annotation_property(horned_owl::vocab::RDFS::Label.iri_s()),
+            av: AnnotationValue::Literal(Literal::Simple { literal: format!("{}#{}", &taxon.text, &ixn.id) }),
+        },
+    )));
+
+    axioms.push(Axiom::DeclareObjectProperty(DeclareObjectProperty(build.object_property(properties.occurs_in.clone()))));
+    axioms.push(Axiom::DeclareNamedIndividual(DeclareNamedIndividual(organism_iri.clone().into())));
+    axioms.push(Axiom::ObjectPropertyAssertion(ObjectPropertyAssertion::new(
+        ObjectPropertyExpression::ObjectProperty(build.object_property(properties.occurs_in.clone())),
+        ixn_individual_iri.clone().into(),
+        organism_iri.clone().into(),
+    )));
+    axioms
+}
+
+/// The PubMed IRI cited through `dc:source`.
+pub fn pm_id_iri(pm_id: i32) -> String {
+    format!("{}/{}", crate::PMID, pm_id)
+}
+
+fn build_object_property_assertion(object_property: &ObjectProperty, ixn_individual_iri: &IRI, actor_individual: &NamedIndividual) -> Vec<Axiom> {
+    vec![
+        Axiom::DeclareObjectProperty(DeclareObjectProperty(object_property.clone())),
+        Axiom::ObjectPropertyAssertion(ObjectPropertyAssertion::new(object_property.clone().into(), ixn_individual_iri.clone().into(), actor_individual.clone())),
+    ]
+}
+
+/// Declares a CTD annotation property together with its label.
+pub fn build_annotation_property(build: &Build, property: &str, label: &str) -> Vec<Axiom> {
+    let annotation_property = build.annotation_property(property);
+    vec![
+        Axiom::DeclareAnnotationProperty(DeclareAnnotationProperty(annotation_property.clone())),
+        Axiom::AnnotationAssertion(AnnotationAssertion::new(
+            annotation_property.0,
+            Annotation { ap: build.annotation_property(horned_owl::vocab::RDFS::Label.iri_s()), av: AnnotationValue::Literal(Literal::Simple { literal: label.to_string() }) },
+        )),
+    ]
+}
+
+fn build_class_assertion(class: &Class, ixn_individual_iri: &IRI) -> Vec<Axiom> {
+    vec![Axiom::DeclareClass(DeclareClass(class.clone())), Axiom::ClassAssertion(ClassAssertion { ce: class.clone().into(), i: ixn_individual_iri.clone().into() })]
+}
+
+/// The link from the acting process to the affected one; `negated` when CTD states the action has no effect.
+struct ProcessLink {
+    property: ObjectProperty,
+    negated: bool,
+}
+
+/// Degrees CTD may add later fall back to plain causally upstream of rather than failing the interaction.
+fn process_to_process(build: &Build, properties: &Properties, ixn: &IXN, degree: Degree) -> ProcessLink {
+    let (property, negated) = match degree {
+        Degree::Increases => (&properties.causally_upstream_of_positive_effect, false),
+        Degree::Decreases => (&properties.causally_upstream_of_negative_effect, false),
+        Degree::Affects => (&properties.causally_upstream_of, false),
+        Degree::NoEffect => (&properties.causally_upstream_of, true),
+        Degree::Unknown(code) => {
+            warn!("ixn {}: unknown degree code {:?}, using causally upstream of", ixn.id, code);
+            (&properties.causally_upstream_of, false)
+        }
+    };
+    ProcessLink { property: build.object_property(property.clone()), negated }
+}
+
+fn build_process_link(link: &ProcessLink, from: &IRI, to: &NamedIndividual) -> Vec<Axiom> {
+    if !link.negated {
+        return build_object_property_assertion(&link.property, from, to);
+    }
+    vec![
+        Axiom::DeclareObjectProperty(DeclareObjectProperty(link.property.clone())),
+        Axiom::NegativeObjectPropertyAssertion(NegativeObjectPropertyAssertion { ope: link.property.clone().into(), from: from.clone().into(), to: to.clone() }),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn actor(actor_type: &str, id: &str, position: i8, parent_id: i32) -> Actor {
+        Actor { text: Some(id.to_string()), ..Actor::new(actor_type.to_string(), id.to_string(), position, parent_id, None, None, None) }
+    }
+
+    fn axn(code: &str, degree_code: char, parent_id: i32) -> AXN {
+        AXN::new(code.to_string(), degree_code, 1, parent_id, String::new())
+    }
+
+    fn ixn(id: i32, axns: Vec<AXN>, actors: Vec<Actor>) -> IXN {
+        IXN { id, taxon: vec![Taxon::new(9606, String::from("Homo sapiens"))], reference: vec![Reference::new(10)], axns, actors }
+    }
+
+    fn converter() -> Converter {
+        Converter::new(ChebiMeshMapping::from(collections::HashMap::new()))
+    }
+
+    /// The causal links between processes, as the local part of the acting process's IRI, the property's local name and whether the link is negated.
+    fn causal_links(axioms: &[Axiom]) -> Vec<(String, String, bool)> {
+        let local = |iri: &IRI| iri.to_string().rsplit(['=', '/']).next().unwrap().to_string();
+        let mut links = axioms
+            .iter()
+            .filter_map(|axiom| match axiom {
+                Axiom::ObjectPropertyAssertion(ObjectPropertyAssertion { ope: ObjectPropertyExpression::ObjectProperty(op), from, .. }) => {
+                    Some((local(&from.0), local(&op.0), false))
+                }
+                Axiom::NegativeObjectPropertyAssertion(NegativeObjectPropertyAssertion { ope: ObjectPropertyExpression::ObjectProperty(op), from, .. }) => {
+                    Some((local(&from.0), local(&op.0), true))
+                }
+                _ => None,
+            })
+            .filter(|(_, property, _)| property.starts_with("RO_00024") || property.starts_with("RO_00023"))
+            .collect_vec();
+        links.sort();
+        links.dedup();
+        links
+    }
+
+    fn chemical_gene(parent_id: i32) -> Vec<Actor> {
+        vec![actor("chemical", "MESH:D000001", 1, parent_id), actor("gene", "GENE:1545", 2, parent_id)]
+    }
+
+    #[test]
+    fn each_degree_picks_its_causal_property() {
+        let converter = converter();
+        let build = Build::new();
+        for (degree_code, property, negated) in [
+            ('+', "RO_0002304", false),
+            ('-', "RO_0002305", false),
+            ('1', "RO_0002411", false),
+            ('^', "RO_0002411", false),
+            ('0', "RO_0002411", true),
+            // unknown degrees fall back to causally upstream of
+            ('?', "RO_0002411", false),
+        ] {
+            let axioms = converter.convert(&build, &ixn(1, vec![axn("exp", degree_code, 1)], chemical_gene(1))).unwrap();
+            assert_eq!(causal_links(&axioms), vec![(String::from("1#0-1-process"), property.to_string(), negated)], "degree {:?}", degree_code);
+        }
+    }
+
+    #[test]
+    fn nested_interactions_use_their_own_degree() {
+        let converter = converter();
+        let build = Build::new();
+        // ixn 2 states that ixn 3, a decrease in expression, has no effect on a gene's activity
+        let nested = Actor { axns: vec![axn("exp", '-', 3)], actors: chemical_gene(3), ..actor("ixn", "3", 1, 2) };
+        let axioms = converter.convert(&build, &ixn(2, vec![axn("act", '0', 2)], vec![nested, actor("gene", "GENE:1546", 2, 2)])).unwrap();
+        assert_eq!(
+            causal_links(&axioms),
+            vec![(String::from("2#0-process"), String::from("RO_0002411"), true), (String::from("3#0-1-process"), String::from("RO_0002305"), false)]
+        );
+    }
+
+    #[test]
+    fn rxn_targets_use_their_own_degree() {
+        let converter = converter();
+        let build = Build::new();
+        let target = Actor { axns: vec![axn("exp", '+', 5)], actors: chemical_gene(5), ..actor("ixn", "5", 2, 4) };
+        let axioms = converter.convert(&build, &ixn(4, vec![axn("rxn", '-', 4)], vec![actor("chemical", "MESH:D000002", 1, 4), target])).unwrap();
+        assert_eq!(
+            causal_links(&axioms),
+            vec![(String::from("4#0-1-process"), String::from("RO_0002305"), false), (String::from("5#0-1-process"), String::from("RO_0002304"), false)]
+        );
+    }
+
+    #[test]
+    fn unregistered_actor_types_are_skipped_and_counted() {
+        let build = Build::new();
+        let protein = ixn(6, vec![axn("exp", '+', 6)], vec![actor("chemical", "MESH:D000001", 1, 6), actor("protein", "PR:1", 2, 6)]);
+        let converter = converter();
+        assert_eq!(converter.convert(&build, &protein).unwrap(), Vec::new());
+        assert!(!converter.convert(&build, &ixn(7, vec![axn("exp", '+', 7)], chemical_gene(7))).unwrap().is_empty());
+        let mut report = Vec::new();
+        converter.take_coverage().write_report(&mut report).unwrap();
+        let report = String::from_utf8(report).unwrap();
+        assert!(report.contains("unknown_actor_type\tprotein\t1\t6\n"), "{}", report);
+        // the skipped interaction has no branch
+        assert!(report.contains("branch\tsimple axn\t1\t\n"), "{}", report);
+
+        let strict = converter.with_strict_actor_types(true);
+        match strict.convert(&build, &protein) {
+            Err(CtdError::UnknownActorType { ixn_id: 6, actor_type }) => assert_eq!(actor_type, "protein"),
+            other => panic!("expected an unknown actor type, got {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,302 @@
+use crate::config::Config;
+use crate::model::*;
+use itertools::Itertools;
+use std::collections;
+use std::io;
+
+/// How many example ixn ids are kept per unhandled shape.
+const EXAMPLE_LIMIT: usize = 5;
+
+/// The conversion branch taken for an interaction's axns and actors.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Branch {
+    Cotreatment,
+    Binding,
+    Rxn,
+    SimpleAxn,
+}
+
+impl Branch {
+    /// The branch the converter handles these axns and actors with, `None` if it does not recognize the shape.
+    pub fn classify(config: &Config, axns: &[AXN], actors: &[Actor]) -> Option<Branch> {
+        let atomic_actors = actors.iter().all(|a| a.actor_type.as_str() != "ixn");
+        if axns.iter().all(|a| a.code.as_str() == "w" && atomic_actors) {
+            return Some(Branch::Cotreatment);
+        }
+        if axns.iter().all(|a| a.code.as_str() == "b" && atomic_actors) {
+            return Some(Branch::Binding);
+        }
+        if axns.iter().all(|a| a.code.as_str() == "rxn") && actors.len() == 2 && actors[1].actor_type == "ixn" {
+            return Some(Branch::Rxn);
+        }
+        if axns.iter().any(|a| config.axn_codes.contains(&a.code)) && actors.len() == 2 && actors[1].actor_type != "ixn" {
+            return Some(Branch::SimpleAxn);
+        }
+        None
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Branch::Cotreatment => "cotreatment",
+            Branch::Binding => "binding",
+            Branch::Rxn => "rxn",
+            Branch::SimpleAxn => "simple axn",
+        }
+    }
+}
+
+/// A compact description of an interaction's structure, e.g. `rxn(chemical,ixn:exp(chemical,gene))`.
+pub fn shape(axns: &[AXN], actors: &[Actor]) -> String {
+    let codes = axns.iter().map(|a| a.code.as_str()).join("|");
+    let actor_shapes = actors
+        .iter()
+        .map(|actor| match actor.actor_type.as_str() {
+            "ixn" => format!("ixn:{}", shape(&actor.axns, &actor.actors)),
+            actor_type => actor_type.to_string(),
+        })
+        .join(",");
+    format!("{}({})", codes, actor_shapes)
+}
+
+/// Shapes of nested interactions that the converter reaches but cannot handle, following the same recursion as `process_actor`.
+fn unhandled_nested_shapes(config: &Config, axns: &[AXN], actors: &[Actor]) -> Vec<String> {
+    let nested = match Branch::classify(config, axns, actors) {
+        Some(Branch::Rxn) => actors.iter().collect_vec(),
+        Some(Branch::SimpleAxn) => actors.iter().take(1).filter(|a| a.actor_type == "ixn").collect_vec(),
+        _ => Vec::new(),
+    };
+    let mut shapes = Vec::new();
+    for actor in nested.into_iter().filter(|a| a.actor_type == "ixn") {
+        match Branch::classify(config, &actor.axns, &actor.actors) {
+            Some(_) => shapes.append(&mut unhandled_nested_shapes(config, &actor.axns, &actor.actors)),
+            None => shapes.push(shape(&actor.axns, &actor.actors)),
+        }
+    }
+    shapes
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+struct Unhandled {
+    count: usize,
+    examples: Vec<i32>,
+}
+
+/// What fraction of the input the converter understood; filled in while converting and written as a report at the end of a run.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Coverage {
+    branches: collections::BTreeMap<Branch, usize>,
+    unhandled: collections::BTreeMap<String, Unhandled>,
+    unmapped_codes: collections::BTreeMap<String, usize>,
+    unknown_degrees: collections::BTreeMap<char, usize>,
+    unknown_actor_types: collections::BTreeMap<String, Unhandled>,
+    mesh_fallbacks: collections::BTreeMap<String, usize>,
+}
+
+impl Coverage {
+    pub fn record_branch(&mut self, branch: Branch) {
+        *self.branches.entry(branch).or_default() += 1;
+    }
+
+    pub fn record_unhandled(&mut self, shape: String, ixn_id: i32) {
+        let unhandled = self.unhandled.entry(shape).or_default();
+        unhandled.count += 1;
+        if unhandled.examples.len() < EXAMPLE_LIMIT && !unhandled.examples.contains(&ixn_id) {
+            unhandled.examples.push(ixn_id);
+        }
+    }
+
+    /// Records the branch of an interaction, any shape the converter skips at any nesting level, codes without a class mapping and unknown degree codes.
+    pub fn record_ixn(&mut self, ixn: &IXN, config: &Config) {
+        match Branch::classify(config, &ixn.axns, &ixn.actors) {
+            Some(branch) => self.record_branch(branch),
+            None => self.record_unhandled(shape(&ixn.axns, &ixn.actors), ixn.id),
+        }
+        for nested_shape in unhandled_nested_shapes(config, &ixn.axns, &ixn.actors) {
+            self.record_unhandled(nested_shape, ixn.id);
+        }
+        let axns = || ixn.axns.iter().chain(ixn.actors.iter().flat_map(|a| a.flat()).flat_map(|a| a.axns.iter()));
+        let mut codes = axns().map(|a| a.code.as_str()).collect_vec();
+        codes.sort_unstable();
+        codes.dedup();
+        // rxn only nests interactions and never needs a class
+        codes.into_iter().filter(|code| *code != "rxn" && !config.class_map.contains_key(*code)).for_each(|code| self.record_unmapped_code(code));
+        let mut unknown_degrees = axns()
+            .filter_map(|a| match a.degree() {
+                Degree::Unknown(code) => Some(code),
+                _ => None,
+            })
+            .collect_vec();
+        unknown_degrees.sort_unstable();
+        unknown_degrees.dedup();
+        unknown_degrees.into_iter().for_each(|code| *self.unknown_degrees.entry(code).or_default() += 1);
+    }
+
+    pub fn record_unmapped_code(&mut self, code: &str) {
+        *self.unmapped_codes.entry(code.to_string()).or_default() += 1;
+    }
+
+    /// Records an actor type with no registry entry; the interaction it occurs in is rejected.
+    pub fn record_unknown_actor_type(&mut self, actor_type: &str, ixn_id: i32) {
+        let unknown = self.unknown_actor_types.entry(actor_type.to_string()).or_default();
+        unknown.count += 1;
+        if unknown.examples.len() < EXAMPLE_LIMIT && !unknown.examples.contains(&ixn_id) {
+            unknown.examples.push(ixn_id);
+        }
+    }
+
+    /// Records an interaction with a chemical that kept its MESH class because the mapping had no (unambiguous) CHEBI id for it.
+    pub fn record_mesh_fallback(&mut self, mesh_id: &str) {
+        *self.mesh_fallbacks.entry(mesh_id.to_string()).or_default() += 1;
+    }
+
+    pub fn unhandled_count(&self) -> usize {
+        self.unhandled.values().map(|u| u.count).sum()
+    }
+
+    /// TSV with one row per handled branch, unhandled shape, unmapped code, unknown degree, unknown actor type and chemical that fell back to MESH, most frequent first within each kind.
+    pub fn write_report<W: io::Write>(&self, write: &mut W) -> io::Result<()> {
+        writeln!(write, "kind\tkey\tcount\texample_ixn_ids")?;
+        for (branch, count) in self.branches.iter().sorted_by(|a, b| b.1.cmp(a.1).then(a.0.cmp(b.0))) {
+            writeln!(write, "branch\t{}\t{}\t", branch.name(), count)?;
+        }
+        for (shape, unhandled) in self.unhandled.iter().sorted_by(|a, b| b.1.count.cmp(&a.1.count).then(a.0.cmp(b.0))) {
+            writeln!(write, "unhandled_shape\t{}\t{}\t{}", shape, unhandled.count, unhandled.examples.iter().join(","))?;
+        }
+        for (code, count) in self.unmapped_codes.iter().sorted_by(|a, b| b.1.cmp(a.1).then(a.0.cmp(b.0))) {
+            writeln!(write, "unmapped_code\t{}\t{}\t", code, count)?;
+        }
+        for (code, count) in self.unknown_degrees.iter().sorted_by(|a, b| b.1.cmp(a.1).then(a.0.cmp(b.0))) {
+            writeln!(write, "unknown_degree\t{}\t{}\t", code, count)?;
+        }
+        for (actor_type, unknown) in self.unknown_actor_types.iter().sorted_by(|a, b| b.1.count.cmp(&a.1.count).then(a.0.cmp(b.0))) {
+            writeln!(write, "unknown_actor_type\t{}\t{}\t{}", actor_type, unknown.count, unknown.examples.iter().join(","))?;
+        }
+        for (mesh_id, count) in self.mesh_fallbacks.iter().sorted_by(|a, b| b.1.cmp(a.1).then(a.0.cmp(b.0))) {
+            writeln!(write, "mesh_fallback\t{}\t{}\t", mesh_id, count)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::converter::Converter;
+    use crate::mapping::ChebiMeshMapping;
+
+    fn axns(codes: &[&str]) -> Vec<AXN> {
+        codes.iter().enumerate().map(|(i, code)| AXN::new(code.to_string(), '+', i as i8 + 1, 1, String::new())).collect()
+    }
+
+    fn actor(actor_type: &str, id: &str, position: i8) -> Actor {
+        Actor::new(actor_type.to_string(), id.to_string(), position, 1, None, None, None)
+    }
+
+    fn nested(codes: &[&str], actors: Vec<Actor>, position: i8) -> Actor {
+        Actor { axns: axns(codes), actors, ..actor("ixn", "", position) }
+    }
+
+    fn chemical_gene() -> Vec<Actor> {
+        vec![actor("chemical", "MESH:D000001", 1), actor("gene", "GENE:1545", 2)]
+    }
+
+    fn ixn(id: i32, codes: &[&str], actors: Vec<Actor>) -> IXN {
+        IXN { id, axns: axns(codes), actors, ..IXN::new() }
+    }
+
+    fn report(coverage: &Coverage) -> Vec<String> {
+        let mut buf = Vec::new();
+        coverage.write_report(&mut buf).unwrap();
+        String::from_utf8(buf).unwrap().lines().skip(1).map(str::to_string).collect()
+    }
+
+    #[test]
+    fn classify_recognizes_each_branch() {
+        let config = Config::default();
+        assert_eq!(Branch::classify(&config, &axns(&["w"]), &chemical_gene()), Some(Branch::Cotreatment));
+        assert_eq!(Branch::classify(&config, &axns(&["b"]), &chemical_gene()), Some(Branch::Binding));
+        let rxn_actors = vec![actor("chemical", "MESH:D000001", 1), nested(&["exp"], chemical_gene(), 2)];
+        assert_eq!(Branch::classify(&config, &axns(&["rxn"]), &rxn_actors), Some(Branch::Rxn));
+        assert_eq!(Branch::classify(&config, &axns(&["exp", "act"]), &chemical_gene()), Some(Branch::SimpleAxn));
+        // a nested interaction as the subject of a simple axn
+        let subject_ixn = vec![nested(&["b"], chemical_gene(), 1), actor("gene", "GENE:1545", 2)];
+        assert_eq!(Branch::classify(&config, &axns(&["exp"]), &subject_ixn), Some(Branch::SimpleAxn));
+    }
+
+    #[test]
+    fn classify_rejects_unhandled_shapes() {
+        let config = Config::default();
+        // cotreatment and binding only hold atomic actors
+        let with_ixn = vec![actor("chemical", "MESH:D000001", 1), nested(&["exp"], chemical_gene(), 2)];
+        assert_eq!(Branch::classify(&config, &axns(&["w"]), &with_ixn), None);
+        assert_eq!(Branch::classify(&config, &axns(&["b"]), &with_ixn), None);
+        // a simple axn has exactly two actors, the second of them atomic
+        assert_eq!(Branch::classify(&config, &axns(&["exp"]), &with_ixn), None);
+        let three_actors = vec![actor("chemical", "MESH:D000001", 1), actor("gene", "GENE:1545", 2), actor("gene", "GENE:1546", 3)];
+        assert_eq!(Branch::classify(&config, &axns(&["exp"]), &three_actors), None);
+        // rxn needs a nested interaction as its second actor
+        assert_eq!(Branch::classify(&config, &axns(&["rxn"]), &chemical_gene()), None);
+        // a code outside axn_codes
+        assert_eq!(Branch::classify(&config, &axns(&["xyz"]), &chemical_gene()), None);
+    }
+
+    #[test]
+    fn shape_describes_nested_interactions() {
+        let actors = vec![actor("chemical", "MESH:D000001", 1), nested(&["exp", "act"], chemical_gene(), 2)];
+        assert_eq!(shape(&axns(&["rxn"]), &actors), "rxn(chemical,ixn:exp|act(chemical,gene))");
+    }
+
+    #[test]
+    fn record_ixn_counts_branches_and_unhandled_shapes() {
+        let config = Config::default();
+        let mut coverage = Coverage::default();
+        coverage.record_ixn(&ixn(1, &["exp"], chemical_gene()), &config);
+        coverage.record_ixn(&ixn(2, &["exp"], chemical_gene()), &config);
+        coverage.record_ixn(&ixn(3, &["b"], chemical_gene()), &config);
+        // the rxn is handled, the three-actor interaction nested in it is not
+        let three_actors = vec![actor("chemical", "MESH:D000001", 1), actor("gene", "GENE:1545", 2), actor("gene", "GENE:1546", 3)];
+        coverage.record_ixn(&ixn(4, &["rxn"], vec![actor("chemical", "MESH:D000002", 1), nested(&["exp"], three_actors, 2)]), &config);
+        for id in 10..20 {
+            coverage.record_ixn(&ixn(id, &["exp"], vec![actor("chemical", "MESH:D000001", 1), actor("gene", "GENE:1545", 2), actor("gene", "GENE:1546", 3)]), &config);
+        }
+        assert_eq!(coverage.unhandled_count(), 11);
+        assert_eq!(report(&coverage), vec!["branch\tsimple axn\t2\t", "branch\tbinding\t1\t", "branch\trxn\t1\t", "unhandled_shape\texp(chemical,gene,gene)\t11\t4,10,11,12,13",]);
+    }
+
+    #[test]
+    fn record_ixn_counts_unmapped_codes_and_unknown_degrees_once_per_interaction() {
+        let config = Config::default();
+        let mut coverage = Coverage::default();
+        let mut unusual = ixn(1, &["exp", "xyz", "xyz"], chemical_gene());
+        unusual.axns.iter_mut().for_each(|axn| axn.degree_code = '?');
+        coverage.record_ixn(&unusual, &config);
+        coverage.record_ixn(&ixn(2, &["xyz"], vec![nested(&["xyz"], chemical_gene(), 1), actor("gene", "GENE:1545", 2)]), &config);
+        let report = report(&coverage);
+        assert!(report.contains(&String::from("unmapped_code\txyz\t2\t")), "{:?}", report);
+        assert!(report.contains(&String::from("unknown_degree\t?\t1\t")), "{:?}", report);
+        assert!(!report.iter().any(|row| row.contains("\texp\t")), "{:?}", report);
+    }
+
+    #[test]
+    fn unknown_actor_types_keep_example_ids() {
+        let mut coverage = Coverage::default();
+        (1..=7).for_each(|id| coverage.record_unknown_actor_type("protein", id));
+        coverage.record_unknown_actor_type("protein", 1);
+        coverage.record_unknown_actor_type("rna", 9);
+        assert_eq!(report(&coverage), vec!["unknown_actor_type\tprotein\t8\t1,2,3,4,5", "unknown_actor_type\trna\t1\t9"]);
+    }
+
+    #[test]
+    fn mesh_fallbacks_are_counted_per_interaction() {
+        let mapping = ChebiMeshMapping::from(collections::HashMap::from([(String::from("MESH:D000001"), String::from("CHEBI:1"))]));
+        let converter = Converter::new(mapping);
+        let build = horned_owl::model::Build::new();
+        // MESH:D000002 occurs twice in the first interaction, once in the second
+        let twice = vec![actor("chemical", "MESH:D000002", 1), nested(&["exp"], vec![actor("chemical", "MESH:D000002", 1), actor("gene", "GENE:1545", 2)], 2)];
+        converter.convert(&build, &ixn(1, &["rxn"], twice)).unwrap();
+        converter.convert(&build, &ixn(2, &["exp"], vec![actor("chemical", "MESH:D000002", 1), actor("gene", "GENE:1545", 2)])).unwrap();
+        converter.convert(&build, &ixn(3, &["exp"], chemical_gene())).unwrap();
+        let coverage = converter.take_coverage();
+        assert_eq!(coverage.mesh_fallbacks, collections::BTreeMap::from([(String::from("MESH:D000002"), 2)]));
+    }
+}
@@ -0,0 +1,153 @@
+use crate::model::*;
+use itertools::Itertools;
+use std::collections;
+use std::io;
+
+/// An interaction present in both releases whose content differs.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChangedIxn {
+    pub old: IXN,
+    pub new: IXN,
+    /// Which parts differ: `taxa`, `references`, `axns` and/or `actors`.
+    pub parts: Vec<&'static str>,
+}
+
+/// Interactions added, removed and changed between two releases, matched by ixn id.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct IxnDiff {
+    pub added: Vec<IXN>,
+    pub removed: Vec<IXN>,
+    pub changed: Vec<ChangedIxn>,
+    pub unchanged: usize,
+}
+
+impl IxnDiff {
+    pub fn compare(old: IXNSet, new: IXNSet) -> IxnDiff {
+        let mut old_by_id: collections::HashMap<i32, IXN> = old.ixns.into_iter().map(|ixn| (ixn.id, ixn)).collect();
+        let mut diff = IxnDiff::default();
+        for new_ixn in new.ixns {
+            match old_by_id.remove(&new_ixn.id) {
+                Some(old_ixn) => {
+                    let parts = changed_parts(&old_ixn, &new_ixn);
+                    if parts.is_empty() {
+                        diff.unchanged += 1;
+                    } else {
+                        diff.changed.push(ChangedIxn { old: old_ixn, new: new_ixn, parts });
+                    }
+                }
+                None => diff.added.push(new_ixn),
+            }
+        }
+        diff.removed = old_by_id.into_values().sorted_by_key(|ixn| ixn.id).collect();
+        diff.added.sort_by_key(|ixn| ixn.id);
+        diff.changed.sort_by_key(|c| c.new.id);
+        diff
+    }
+
+    /// Interactions whose axioms a downstream store has to add: the added ones and the new version of changed ones.
+    pub fn to_add(&self) -> Vec<IXN> {
+        self.added.iter().cloned().chain(self.changed.iter().map(|c| c.new.clone())).collect()
+    }
+
+    /// Interactions whose axioms a downstream store has to remove: the removed ones and the old version of changed ones.
+    pub fn to_remove(&self) -> Vec<IXN> {
+        self.removed.iter().cloned().chain(self.changed.iter().map(|c| c.old.clone())).collect()
+    }
+
+    /// TSV with one row per added, removed or changed interaction.
+    pub fn write_report<W: io::Write>(&self, write: &mut W) -> io::Result<()> {
+        writeln!(write, "change\tixn_id\tparts")?;
+        for ixn in self.added.iter() {
+            writeln!(write, "added\t{}\t", ixn.id)?;
+        }
+        for ixn in self.removed.iter() {
+            writeln!(write, "removed\t{}\t", ixn.id)?;
+        }
+        for changed in self.changed.iter() {
+            writeln!(write, "changed\t{}\t{}", changed.new.id, changed.parts.join(","))?;
+        }
+        Ok(())
+    }
+}
+
+fn changed_parts(old: &IXN, new: &IXN) -> Vec<&'static str> {
+    let mut parts = Vec::new();
+    if old.taxon != new.taxon {
+        parts.push("taxa");
+    }
+    if old.reference != new.reference {
+        parts.push("references");
+    }
+    if old.axns != new.axns {
+        parts.push("axns");
+    }
+    if old.actors != new.actors {
+        parts.push("actors");
+    }
+    parts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ixn(id: i32, pm_id: i32, code: &str) -> IXN {
+        IXN {
+            id,
+            taxon: vec![Taxon::new(9606, String::from("Homo sapiens"))],
+            reference: vec![Reference::new(pm_id)],
+            axns: vec![AXN::new(code.to_string(), '+', 1, id, String::new())],
+            actors: vec![Actor::new(String::from("chemical"), String::from("MESH:D000001"), 1, id, None, None, None)],
+        }
+    }
+
+    fn ids(ixns: &[IXN]) -> Vec<i32> {
+        ixns.iter().map(|ixn| ixn.id).collect()
+    }
+
+    /// 1 and 5 removed, 2 unchanged, 3 and 4 changed, 6 and 7 added.
+    fn diff() -> IxnDiff {
+        let old = IXNSet { ixns: vec![ixn(5, 1, "exp"), ixn(1, 1, "exp"), ixn(2, 1, "exp"), ixn(3, 1, "exp"), ixn(4, 1, "exp")] };
+        let new = IXNSet { ixns: vec![ixn(7, 1, "exp"), ixn(4, 2, "act"), ixn(2, 1, "exp"), ixn(6, 1, "exp"), ixn(3, 2, "exp")] };
+        IxnDiff::compare(old, new)
+    }
+
+    #[test]
+    fn classifies_interactions_by_id() {
+        let diff = diff();
+        assert_eq!(ids(&diff.added), vec![6, 7]);
+        assert_eq!(ids(&diff.removed), vec![1, 5]);
+        assert_eq!(diff.changed.iter().map(|c| (c.new.id, c.parts.clone())).collect_vec(), vec![(3, vec!["references"]), (4, vec!["references", "axns"])]);
+        assert_eq!(diff.unchanged, 1);
+    }
+
+    #[test]
+    fn changed_interactions_keep_both_versions() {
+        let diff = diff();
+        let changed = &diff.changed[1];
+        assert_eq!((changed.old.axns[0].code.as_str(), changed.new.axns[0].code.as_str()), ("exp", "act"));
+        assert_eq!(ids(&diff.to_add()), vec![6, 7, 3, 4]);
+        assert_eq!(ids(&diff.to_remove()), vec![1, 5, 3, 4]);
+        assert_eq!(diff.to_remove()[3], changed.old);
+    }
+
+    #[test]
+    fn reports_taxa_and_actor_changes() {
+        let old = ixn(1, 1, "exp");
+        let mut new = old.clone();
+        new.taxon.push(Taxon::new(10090, String::from("Mus musculus")));
+        new.actors[0].form = Some(String::from("protein"));
+        let diff = IxnDiff::compare(IXNSet { ixns: vec![old] }, IXNSet { ixns: vec![new] });
+        assert_eq!(diff.changed[0].parts, vec!["taxa", "actors"]);
+    }
+
+    #[test]
+    fn writes_one_report_row_per_difference() {
+        let mut report = Vec::new();
+        diff().write_report(&mut report).unwrap();
+        assert_eq!(
+            String::from_utf8(report).unwrap(),
+            "change\tixn_id\tparts\nadded\t6\t\nadded\t7\t\nremoved\t1\t\nremoved\t5\t\nchanged\t3\treferences\nchanged\t4\treferences,axns\n"
+        );
+    }
+}
@@ -0,0 +1,105 @@
+use std::error;
+use std::fmt;
+
+/// Errors raised while reading or converting a single CTD interaction.
+///
+/// Every variant except `Xml` and `Io` is scoped to one `<ixn>`, JSON line or table row, so the caller can log it and move on to the next interaction.
+#[derive(Debug, Clone, PartialEq)]
+pub enum CtdError {
+    /// An element of an `<ixn>` without one of its attributes; `element` is its path, e.g. `ixn.actor[1]`.
+    MissingAttribute {
+        ixn_id: Option<i32>,
+        element: String,
+        attribute: String,
+    },
+    /// An attribute that is not an integer or does not fit its type, e.g. an axn position above 127.
+    BadInteger {
+        ixn_id: Option<i32>,
+        element: String,
+        attribute: String,
+        value: String,
+    },
+    MissingText {
+        ixn_id: Option<i32>,
+        element: String,
+    },
+    /// Any other part of an `<ixn>` that does not match the model, e.g. a degree code of more than one character; `path` may end in an `@attribute`.
+    Deserialize {
+        ixn_id: Option<i32>,
+        path: String,
+        message: String,
+    },
+    MissingAxn {
+        ixn_id: i32,
+    },
+    UnknownActorType {
+        ixn_id: i32,
+        actor_type: String,
+    },
+    /// An association table entity, such as a `MESH:` chemical id, whose actor type has no registry entry.
+    UnregisteredActorType {
+        actor_type: String,
+        id: String,
+    },
+    Xml {
+        message: String,
+    },
+    Json {
+        line: usize,
+        message: String,
+    },
+    /// A row of a CTD association table that does not match its model.
+    Tsv {
+        line: usize,
+        message: String,
+    },
+    Io {
+        message: String,
+    },
+}
+
+impl CtdError {
+    pub fn ixn_id(&self) -> Option<i32> {
+        match self {
+            CtdError::MissingAttribute { ixn_id, .. } | CtdError::BadInteger { ixn_id, .. } | CtdError::MissingText { ixn_id, .. } | CtdError::Deserialize { ixn_id, .. } => {
+                *ixn_id
+            }
+            CtdError::MissingAxn { ixn_id } | CtdError::UnknownActorType { ixn_id, .. } => Some(*ixn_id),
+            CtdError::UnregisteredActorType { .. } | CtdError::Xml { .. } | CtdError::Json { .. } | CtdError::Tsv { .. } | CtdError::Io { .. } => None,
+        }
+    }
+
+    /// Whether the remaining input can still be read after this error.
+    pub fn is_recoverable(&self) -> bool {
+        !matches!(self, CtdError::Xml { .. } | CtdError::Io { .. })
+    }
+}
+
+impl fmt::Display for CtdError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some(ixn_id) = self.ixn_id() {
+            write!(f, "ixn {}: ", ixn_id)?;
+        }
+        match self {
+            CtdError::MissingAttribute { element, attribute, .. } => write!(f, "{} is missing attribute '{}'", element, attribute),
+            CtdError::BadInteger { element, attribute, value, .. } => write!(f, "{} attribute '{}' is not an integer in range: {:?}", element, attribute, value),
+            CtdError::MissingText { element, .. } => write!(f, "{} has no text", element),
+            CtdError::Deserialize { path, message, .. } => write!(f, "{} does not match the model: {}", path, message),
+            CtdError::MissingAxn { .. } => write!(f, "no axn"),
+            CtdError::UnknownActorType { actor_type, .. } => write!(f, "unknown actor type {:?}", actor_type),
+            CtdError::UnregisteredActorType { actor_type, id } => write!(f, "{}: actor type {:?} is not registered", id, actor_type),
+            CtdError::Xml { message } => write!(f, "malformed xml: {}", message),
+            CtdError::Json { line, message } => write!(f, "line {}: malformed json: {}", line, message),
+            CtdError::Tsv { line, message } => write!(f, "line {}: {}", line, message),
+            CtdError::Io { message } => write!(f, "read error: {}", message),
+        }
+    }
+}
+
+impl error::Error for CtdError {}
+
+impl From<quick_xml::Error> for CtdError {
+    fn from(e: quick_xml::Error) -> CtdError {
+        CtdError::Xml { message: e.to_string() }
+    }
+}
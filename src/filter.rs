@@ -0,0 +1,162 @@
+use crate::model::*;
+use std::collections;
+use std::error;
+use std::io::BufRead;
+use std::path;
+
+/// Selects interactions by taxon, reference, actor id, action code and degree code.
+///
+/// Every non-empty criterion must match (AND); within a criterion any one value is enough (OR). An empty filter keeps everything.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct IxnFilter {
+    /// NCBI taxon ids, e.g. `9606`.
+    pub taxa: collections::HashSet<i32>,
+    pub pm_ids: collections::HashSet<i32>,
+    /// Actor ids keyed by actor type, e.g. `chemical` to `MESH:D000009`; the CTD prefix may be left out.
+    pub actor_ids: collections::BTreeMap<String, collections::HashSet<String>>,
+    /// Action codes, matched against the axns of the interaction and of any nested interaction.
+    pub codes: collections::HashSet<String>,
+    pub degree_codes: collections::HashSet<char>,
+}
+
+impl IxnFilter {
+    pub fn is_empty(&self) -> bool {
+        self.taxa.is_empty() && self.pm_ids.is_empty() && self.actor_ids.values().all(|ids| ids.is_empty()) && self.codes.is_empty() && self.degree_codes.is_empty()
+    }
+
+    pub fn matches(&self, ixn: &IXN) -> bool {
+        if !self.taxa.is_empty() && !ixn.taxon.iter().any(|t| self.taxa.contains(&t.id)) {
+            return false;
+        }
+        if !self.pm_ids.is_empty() && !ixn.reference.iter().any(|r| self.pm_ids.contains(&r.pm_id)) {
+            return false;
+        }
+        let actors = || ixn.actors.iter().flat_map(|a| a.flat());
+        for (actor_type, ids) in self.actor_ids.iter().filter(|(_, ids)| !ids.is_empty()) {
+            let matched = actors().filter(|a| &a.actor_type == actor_type).any(|a| {
+                let local_id = a.id.split_once(':').map(|(_, local)| local).unwrap_or(&a.id);
+                ids.contains(&a.id) || ids.contains(local_id)
+            });
+            if !matched {
+                return false;
+            }
+        }
+        let axns = || ixn.axns.iter().chain(actors().flat_map(|a| a.axns.iter()));
+        if !self.codes.is_empty() && !axns().any(|a| self.codes.contains(&a.code)) {
+            return false;
+        }
+        if !self.degree_codes.is_empty() && !axns().any(|a| self.degree_codes.contains(&a.degree_code)) {
+            return false;
+        }
+        true
+    }
+}
+
+/// Reads one id per line, ignoring blank lines and `#` comments; the file may be gzip-compressed.
+pub fn read_id_list(list_path: &path::Path) -> Result<Vec<String>, Box<dyn error::Error>> {
+    let mut ids = Vec::new();
+    for line in crate::parser::open_input(list_path)?.lines() {
+        let line = line?;
+        let id = line.trim();
+        if !id.is_empty() && !id.starts_with('#') {
+            ids.push(id.to_string());
+        }
+    }
+    Ok(ids)
+}
+
+/// Expands command line values, where `@<file>` stands for the ids listed in that file.
+pub fn expand_values(values: &[String]) -> Result<Vec<String>, Box<dyn error::Error>> {
+    let mut expanded = Vec::new();
+    for value in values {
+        match value.strip_prefix('@') {
+            Some(list_path) => expanded.append(&mut read_id_list(path::Path::new(list_path))?),
+            None => expanded.push(value.clone()),
+        }
+    }
+    Ok(expanded)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn axn(code: &str, degree_code: char, parent_id: i32) -> AXN {
+        AXN::new(code.to_string(), degree_code, 1, parent_id, String::new())
+    }
+
+    fn actor(actor_type: &str, id: &str, parent_id: i32) -> Actor {
+        Actor::new(actor_type.to_string(), id.to_string(), 1, parent_id, None, None, None)
+    }
+
+    /// Chemical MESH:D000001 affects the expression of gene 1, itself increased by chemical MESH:D000002, in human and mouse.
+    fn ixn() -> IXN {
+        let mut nested = actor("ixn", "2", 1);
+        nested.axns.push(axn("exp", '+', 2));
+        nested.actors = vec![actor("chemical", "MESH:D000002", 2), actor("gene", "GENE:1", 2)];
+        IXN {
+            id: 1,
+            taxon: vec![Taxon::new(9606, String::from("Homo sapiens")), Taxon::new(10090, String::from("Mus musculus"))],
+            reference: vec![Reference::new(100)],
+            axns: vec![axn("rxn", '1', 1)],
+            actors: vec![actor("chemical", "MESH:D000001", 1), nested],
+        }
+    }
+
+    fn set<T: Clone + Eq + std::hash::Hash>(values: &[T]) -> collections::HashSet<T> {
+        values.iter().cloned().collect()
+    }
+
+    fn actor_ids(actor_type: &str, ids: &[&str]) -> collections::BTreeMap<String, collections::HashSet<String>> {
+        collections::BTreeMap::from([(actor_type.to_string(), ids.iter().map(|id| id.to_string()).collect())])
+    }
+
+    #[test]
+    fn an_empty_filter_keeps_everything() {
+        let filter = IxnFilter { actor_ids: actor_ids("gene", &[]), ..IxnFilter::default() };
+        assert!(filter.is_empty());
+        assert!(filter.matches(&ixn()));
+    }
+
+    #[test]
+    fn any_value_of_a_criterion_matches() {
+        assert!(IxnFilter { taxa: set(&[1, 10090]), ..IxnFilter::default() }.matches(&ixn()));
+        assert!(!IxnFilter { taxa: set(&[10116]), ..IxnFilter::default() }.matches(&ixn()));
+        assert!(IxnFilter { pm_ids: set(&[100, 200]), ..IxnFilter::default() }.matches(&ixn()));
+        assert!(!IxnFilter { pm_ids: set(&[200]), ..IxnFilter::default() }.matches(&ixn()));
+    }
+
+    #[test]
+    fn every_criterion_must_match() {
+        let filter = IxnFilter { taxa: set(&[9606]), pm_ids: set(&[200]), ..IxnFilter::default() };
+        assert!(!filter.is_empty());
+        assert!(!filter.matches(&ixn()));
+    }
+
+    #[test]
+    fn actor_ids_match_nested_actors_with_or_without_the_prefix() {
+        assert!(IxnFilter { actor_ids: actor_ids("gene", &["GENE:1"]), ..IxnFilter::default() }.matches(&ixn()));
+        assert!(IxnFilter { actor_ids: actor_ids("gene", &["1"]), ..IxnFilter::default() }.matches(&ixn()));
+        assert!(IxnFilter { actor_ids: actor_ids("chemical", &["D000002"]), ..IxnFilter::default() }.matches(&ixn()));
+        assert!(!IxnFilter { actor_ids: actor_ids("gene", &["D000001"]), ..IxnFilter::default() }.matches(&ixn()));
+    }
+
+    #[test]
+    fn codes_match_the_axns_of_nested_interactions() {
+        assert!(IxnFilter { codes: set(&[String::from("exp")]), ..IxnFilter::default() }.matches(&ixn()));
+        assert!(!IxnFilter { codes: set(&[String::from("b")]), ..IxnFilter::default() }.matches(&ixn()));
+        assert!(IxnFilter { degree_codes: set(&['+']), ..IxnFilter::default() }.matches(&ixn()));
+        assert!(!IxnFilter { degree_codes: set(&['-']), ..IxnFilter::default() }.matches(&ixn()));
+    }
+
+    #[test]
+    fn expand_values_reads_at_files() {
+        let list_path = std::env::temp_dir().join(format!("ctd-to-owl-{}-ids.txt", std::process::id()));
+        fs::write(&list_path, "# genes of interest\nGENE:1\n\n  GENE:2  \n").unwrap();
+        let values = vec![String::from("GENE:0"), format!("@{}", list_path.display()), String::from("GENE:3")];
+        assert_eq!(expand_values(&values).unwrap(), vec!["GENE:0", "GENE:1", "GENE:2", "GENE:3"]);
+        fs::remove_file(&list_path).unwrap();
+        assert!(expand_values(&values).is_err());
+    }
+}
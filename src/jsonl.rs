@@ -0,0 +1,45 @@
+use crate::error::CtdError;
+use crate::model::*;
+use std::io;
+
+/// Writes an interaction as one line of JSON Lines; interactions and nested `ixn` actors both keep their axns under `axns` and their actors under `actors`.
+pub fn write_ixn<W: io::Write>(write: &mut W, ixn: &IXN) -> io::Result<()> {
+    serde_json::to_writer(&mut *write, ixn)?;
+    writeln!(write)
+}
+
+/// Reads interactions written by `write_ixn`, one JSON object per line; blank lines are skipped.
+pub struct JsonLinesReader<R: io::BufRead> {
+    input: R,
+    line: String,
+    line_number: usize,
+    done: bool,
+}
+
+impl<R: io::BufRead> JsonLinesReader<R> {
+    pub fn new(input: R) -> JsonLinesReader<R> {
+        JsonLinesReader { input, line: String::new(), line_number: 0, done: false }
+    }
+}
+
+/// Yields `Err` for a line that is not a valid interaction and carries on with the next one; a read error ends the iteration.
+impl<R: io::BufRead> Iterator for JsonLinesReader<R> {
+    type Item = Result<IXN, CtdError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while !self.done {
+            self.line.clear();
+            self.line_number += 1;
+            match self.input.read_line(&mut self.line) {
+                Ok(0) => self.done = true,
+                Ok(_) if self.line.trim().is_empty() => {}
+                Ok(_) => return Some(serde_json::from_str(&self.line).map_err(|e| CtdError::Json { line: self.line_number, message: e.to_string() })),
+                Err(e) => {
+                    self.done = true;
+                    return Some(Err(CtdError::Io { message: e.to_string() }));
+                }
+            }
+        }
+        None
+    }
+}
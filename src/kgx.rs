@@ -0,0 +1,359 @@
+use crate::converter::Converter;
+use crate::error::CtdError;
+use crate::model::*;
+use itertools::Itertools;
+use std::collections;
+use std::io;
+
+const NODE_COLUMNS: [&str; 4] = ["id", "category", "name", "provided_by"];
+const EDGE_COLUMNS: [&str; 12] = [
+    "id",
+    "subject",
+    "predicate",
+    "object",
+    "category",
+    "qualified_predicate",
+    "object_aspect_qualifier",
+    "object_direction_qualifier",
+    "species_context_qualifier",
+    "negated",
+    "publications",
+    "primary_knowledge_source",
+];
+
+const KNOWLEDGE_SOURCE: &str = "infores:ctd";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Node {
+    pub id: String,
+    pub category: String,
+    pub name: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Edge {
+    pub id: String,
+    pub subject: String,
+    pub predicate: String,
+    pub object: String,
+    pub category: String,
+    pub qualified_predicate: Option<String>,
+    pub object_aspect_qualifier: Option<String>,
+    pub object_direction_qualifier: Option<String>,
+    pub species_context_qualifier: Option<String>,
+    pub negated: bool,
+    pub publications: Vec<String>,
+}
+
+/// Maps interactions onto Biolink nodes and edges, resolving actors the same way as the OWL converter.
+///
+/// Only interactions between two atomic chemical or gene actors become edges; nested interactions (rxn, cotreatment) have no flat Biolink equivalent and yield none.
+pub struct KgxExporter<'a> {
+    converter: &'a Converter,
+}
+
+impl<'a> KgxExporter<'a> {
+    pub fn new(converter: &'a Converter) -> KgxExporter<'a> {
+        KgxExporter { converter }
+    }
+
+    /// Nodes and edges of a chunk of interactions, and the interactions that failed to convert.
+    pub fn convert_chunk(&self, ixns: &[IXN]) -> (Vec<Node>, Vec<Edge>, Vec<CtdError>) {
+        let (mut nodes, mut edges, mut rejects) = (Vec::new(), Vec::new(), Vec::new());
+        for ixn in ixns.iter() {
+            match self.convert(ixn) {
+                Ok((mut ixn_nodes, mut ixn_edges)) => {
+                    nodes.append(&mut ixn_nodes);
+                    edges.append(&mut ixn_edges);
+                }
+                Err(e) => rejects.push(e),
+            }
+        }
+        (nodes, edges, rejects)
+    }
+
+    pub fn convert(&self, ixn: &IXN) -> Result<(Vec<Node>, Vec<Edge>), CtdError> {
+        if !self.converter.check_actor_types(ixn)? {
+            return Ok((Vec::new(), Vec::new()));
+        }
+        self.converter.record_coverage(ixn);
+        if ixn.actors.len() != 2 || ixn.actors.iter().any(|a| a.actor_type == "ixn") {
+            debug!("ixn {}: nested or n-ary interaction has no kgx edge", ixn.id);
+            return Ok((Vec::new(), Vec::new()));
+        }
+        let nodes = ixn.actors.iter().map(|actor| self.node(ixn, actor)).collect::<Result<Vec<_>, _>>()?;
+        let (subject, object) = (&nodes[0], &nodes[1]);
+
+        let species_context_qualifier = match ixn.taxon.as_slice() {
+            [taxon] => Some(format!("NCBITaxon:{}", taxon.id)),
+            _ => None,
+        };
+        let publications = ixn.reference.iter().map(|r| format!("PMID:{}", r.pm_id)).collect_vec();
+
+        let mut edges = Vec::new();
+        // cotreatment states that the actors were given together, not that one acts on the other
+        for axn in ixn.axns.iter().filter(|axn| axn.code != "w") {
+            let (predicate, category, qualified_predicate) = match axn.code.as_str() {
+                "b" => ("biolink:directly_physically_interacts_with", String::from("biolink:Association"), None),
+                _ => ("biolink:affects", association_category(&subject.category, &object.category), Some(String::from("biolink:causes"))),
+            };
+            edges.push(Edge {
+                id: format!("CTD:{}-{}", ixn.id, axn.position),
+                subject: subject.id.clone(),
+                predicate: predicate.to_string(),
+                object: object.id.clone(),
+                category,
+                qualified_predicate,
+                object_aspect_qualifier: aspect(axn.code.as_str()).map(str::to_string),
+                object_direction_qualifier: direction(axn.degree()).map(str::to_string),
+                species_context_qualifier: species_context_qualifier.clone(),
+                negated: axn.degree() == Degree::NoEffect,
+                publications: publications.clone(),
+            });
+        }
+        if edges.is_empty() {
+            return Ok((Vec::new(), Vec::new()));
+        }
+        Ok((nodes, edges))
+    }
+
+    fn node(&self, ixn: &IXN, actor: &Actor) -> Result<Node, CtdError> {
+        let resolved = self.converter.resolve_actor(ixn, actor)?;
+        Ok(Node { id: curie(&resolved.class_iri), category: resolved.category, name: resolved.text })
+    }
+}
+
+/// Writes `edges.tsv` as interactions arrive and keeps nodes, which repeat across interactions, until `finish` writes `nodes.tsv`.
+pub struct KgxWriter<W: io::Write> {
+    edges: W,
+    nodes: collections::BTreeMap<String, Node>,
+    next_chunk: usize,
+    /// Chunks converted before the ones preceding them, keyed by chunk index.
+    pending: collections::BTreeMap<usize, (Vec<Node>, Vec<Edge>)>,
+}
+
+impl<W: io::Write> KgxWriter<W> {
+    pub fn new(mut edges: W) -> io::Result<KgxWriter<W>> {
+        writeln!(edges, "{}", EDGE_COLUMNS.join("\t"))?;
+        Ok(KgxWriter { edges, nodes: collections::BTreeMap::new(), next_chunk: 0, pending: collections::BTreeMap::new() })
+    }
+
+    /// Adds chunk `idx` of a run whose chunks are converted in parallel; edges are written in chunk order, so a chunk waits for those before it.
+    pub fn add_chunk(&mut self, idx: usize, nodes: Vec<Node>, edges: Vec<Edge>) -> io::Result<()> {
+        self.pending.insert(idx, (nodes, edges));
+        while let Some((nodes, edges)) = self.pending.remove(&self.next_chunk) {
+            self.add(nodes, edges)?;
+            self.next_chunk += 1;
+        }
+        Ok(())
+    }
+
+    pub fn add(&mut self, nodes: Vec<Node>, edges: Vec<Edge>) -> io::Result<()> {
+        for node in nodes {
+            self.nodes.entry(node.id.clone()).or_insert(node);
+        }
+        for edge in edges {
+            let row = [
+                edge.id,
+                edge.subject,
+                edge.predicate,
+                edge.object,
+                edge.category,
+                edge.qualified_predicate.unwrap_or_default(),
+                edge.object_aspect_qualifier.unwrap_or_default(),
+                edge.object_direction_qualifier.unwrap_or_default(),
+                edge.species_context_qualifier.unwrap_or_default(),
+                edge.negated.to_string(),
+                edge.publications.join("|"),
+                KNOWLEDGE_SOURCE.to_string(),
+            ];
+            writeln!(self.edges, "{}", row.iter().map(|v| sanitize(v)).join("\t"))?;
+        }
+        Ok(())
+    }
+
+    /// Writes `nodes.tsv`; chunks still waiting for an earlier one, which was never added, are written first.
+    pub fn finish<N: io::Write>(mut self, nodes: &mut N) -> io::Result<()> {
+        for (nodes, edges) in std::mem::take(&mut self.pending).into_values() {
+            self.add(nodes, edges)?;
+        }
+        self.edges.flush()?;
+        writeln!(nodes, "{}", NODE_COLUMNS.join("\t"))?;
+        for node in self.nodes.into_values() {
+            writeln!(nodes, "{}\t{}\t{}\t{}", sanitize(&node.id), sanitize(&node.category), sanitize(&node.name), KNOWLEDGE_SOURCE)?;
+        }
+        nodes.flush()
+    }
+}
+
+fn association_category(subject_category: &str, object_category: &str) -> String {
+    match (subject_category, object_category) {
+        ("biolink:ChemicalEntity", "biolink:Gene") => String::from("biolink:ChemicalAffectsGeneAssociation"),
+        ("biolink:Gene", "biolink:ChemicalEntity") => String::from("biolink:GeneAffectsChemicalAssociation"),
+        _ => String::from("biolink:Association"),
+    }
+}
+
+/// Biolink `GeneOrGeneProductOrChemicalEntityAspectEnum` value for a CTD action code.
+fn aspect(code: &str) -> Option<&'static str> {
+    let aspect = match code {
+        "abu" => "abundance",
+        "ace" => "acetylation",
+        "act" => "activity",
+        "alk" => "alkylation",
+        "clv" => "cleavage",
+        "csy" => "synthesis",
+        "deg" => "degradation",
+        "exp" => "expression",
+        "fol" => "folding",
+        "glc" => "glucuronidation",
+        "gyc" | "ogl" => "glycosylation",
+        "hdx" => "hydroxylation",
+        "imt" | "trt" => "transport",
+        "loc" => "localization",
+        "met" => "metabolic_processing",
+        "mut" => "mutation_rate",
+        "myl" => "methylation",
+        "nit" => "nitrosation",
+        "oxd" => "oxidation",
+        "pho" => "phosphorylation",
+        "red" => "reduction",
+        "sec" => "secretion",
+        "spl" => "splicing",
+        "sta" => "stability",
+        "sum" => "sumoylation",
+        "ubq" => "ubiquitination",
+        "upt" => "uptake",
+        _ => return None,
+    };
+    Some(aspect)
+}
+
+fn direction(degree: Degree) -> Option<&'static str> {
+    match degree {
+        Degree::Increases => Some("increased"),
+        Degree::Decreases => Some("decreased"),
+        _ => None,
+    }
+}
+
+fn curie(iri: &str) -> String {
+    for (namespace, prefix) in [(crate::CHEBI, "CHEBI:"), (crate::MESH, "MESH:"), (crate::NCBIGENE, "NCBIGene:")] {
+        if let Some(local) = iri.strip_prefix(namespace) {
+            return format!("{}{}", prefix, local);
+        }
+    }
+    iri.to_string()
+}
+
+/// KGX TSV has no quoting, so tabs and line breaks inside values are replaced.
+fn sanitize(value: &str) -> String {
+    value.replace(['\t', '\n', '\r'], " ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mapping::ChebiMeshMapping;
+
+    fn actor(actor_type: &str, id: &str, position: i8, text: &str) -> Actor {
+        Actor { text: Some(text.to_string()), ..Actor::new(actor_type.to_string(), id.to_string(), position, 1, None, None, None) }
+    }
+
+    fn ixn(codes: &[(&str, char)], actors: Vec<Actor>) -> IXN {
+        IXN {
+            id: 1,
+            taxon: vec![Taxon::new(9606, String::from("Homo sapiens"))],
+            reference: vec![Reference::new(10), Reference::new(20)],
+            axns: codes.iter().enumerate().map(|(i, (code, degree_code))| AXN::new(code.to_string(), *degree_code, i as i8 + 1, 1, String::new())).collect(),
+            actors,
+        }
+    }
+
+    fn chemical_gene(codes: &[(&str, char)]) -> IXN {
+        ixn(codes, vec![actor("chemical", "MESH:D000001", 1, "Calcimycin"), actor("gene", "GENE:1545", 2, "CYP1B1")])
+    }
+
+    fn mapping() -> ChebiMeshMapping {
+        ChebiMeshMapping::from(collections::HashMap::from([(String::from("MESH:D000001"), String::from("CHEBI:1"))]))
+    }
+
+    #[test]
+    fn affects_edges_carry_causes_as_qualified_predicate() {
+        let converter = Converter::new(mapping());
+        let (nodes, edges) = KgxExporter::new(&converter).convert(&chemical_gene(&[("exp", '+'), ("act", '0')])).unwrap();
+        assert_eq!(
+            nodes.iter().map(|n| (n.id.as_str(), n.category.as_str(), n.name.as_str())).collect_vec(),
+            vec![("CHEBI:1", "biolink:ChemicalEntity", "Calcimycin"), ("NCBIGene:1545", "biolink:Gene", "CYP1B1")]
+        );
+        let edge = &edges[0];
+        assert_eq!((edge.id.as_str(), edge.subject.as_str(), edge.predicate.as_str(), edge.object.as_str()), ("CTD:1-1", "CHEBI:1", "biolink:affects", "NCBIGene:1545"));
+        assert_eq!(edge.category, "biolink:ChemicalAffectsGeneAssociation");
+        assert_eq!(edge.qualified_predicate.as_deref(), Some("biolink:causes"));
+        assert_eq!((edge.object_aspect_qualifier.as_deref(), edge.object_direction_qualifier.as_deref()), (Some("expression"), Some("increased")));
+        assert_eq!(edge.species_context_qualifier.as_deref(), Some("NCBITaxon:9606"));
+        assert_eq!(edge.publications, vec!["PMID:10", "PMID:20"]);
+        assert!(!edge.negated);
+        // no effect is a negated edge without a direction
+        assert_eq!((edges[1].negated, edges[1].object_direction_qualifier.as_deref(), edges[1].object_aspect_qualifier.as_deref()), (true, None, Some("activity")));
+    }
+
+    #[test]
+    fn binding_is_a_direct_physical_interaction() {
+        let converter = Converter::new(mapping());
+        let (_, edges) = KgxExporter::new(&converter).convert(&chemical_gene(&[("b", '1')])).unwrap();
+        assert_eq!(edges.len(), 1);
+        assert_eq!(
+            (edges[0].predicate.as_str(), edges[0].category.as_str(), edges[0].qualified_predicate.as_deref()),
+            ("biolink:directly_physically_interacts_with", "biolink:Association", None)
+        );
+    }
+
+    #[test]
+    fn only_binary_interactions_between_atomic_actors_become_edges() {
+        let converter = Converter::new(mapping());
+        let exporter = KgxExporter::new(&converter);
+        let three_actors = ixn(&[("exp", '+')], vec![actor("chemical", "MESH:D000001", 1, "A"), actor("gene", "GENE:1", 2, "G1"), actor("gene", "GENE:2", 3, "G2")]);
+        let nested = ixn(&[("rxn", '+')], vec![actor("chemical", "MESH:D000001", 1, "A"), actor("ixn", "2", 2, "")]);
+        let cotreatment = ixn(&[("w", '1')], vec![actor("chemical", "MESH:D000001", 1, "A"), actor("chemical", "MESH:D000002", 2, "B")]);
+        for ixn in [three_actors, nested, cotreatment] {
+            assert_eq!(exporter.convert(&ixn).unwrap(), (Vec::new(), Vec::new()));
+        }
+        // interactions without an edge are still in the coverage report
+        let mut report = Vec::new();
+        converter.take_coverage().write_report(&mut report).unwrap();
+        let report = String::from_utf8(report).unwrap();
+        assert!(report.contains("branch\tcotreatment\t1\t") && report.contains("unhandled_shape\texp(chemical,gene,gene)\t1\t1"), "{}", report);
+    }
+
+    #[test]
+    fn nodes_are_written_once_and_edges_in_chunk_order() {
+        let converter = Converter::new(mapping());
+        let exporter = KgxExporter::new(&converter);
+        let (mut edges_tsv, mut nodes_tsv) = (Vec::new(), Vec::new());
+        let mut writer = KgxWriter::new(&mut edges_tsv).unwrap();
+        let mut second = chemical_gene(&[("b", '1')]);
+        second.id = 2;
+        let (nodes, edges, rejects) = exporter.convert_chunk(&[second]);
+        assert!(rejects.is_empty());
+        writer.add_chunk(1, nodes, edges).unwrap();
+        let (nodes, edges, _) = exporter.convert_chunk(&[chemical_gene(&[("exp", '-')])]);
+        writer.add_chunk(0, nodes, edges).unwrap();
+        writer.finish(&mut nodes_tsv).unwrap();
+        let edge_ids = String::from_utf8(edges_tsv).unwrap().lines().skip(1).map(|line| line.split('\t').next().unwrap().to_string()).collect_vec();
+        assert_eq!(edge_ids, vec!["CTD:1-1", "CTD:2-1"]);
+        assert_eq!(
+            String::from_utf8(nodes_tsv).unwrap(),
+            "id\tcategory\tname\tprovided_by\nCHEBI:1\tbiolink:ChemicalEntity\tCalcimycin\tinfores:ctd\nNCBIGene:1545\tbiolink:Gene\tCYP1B1\tinfores:ctd\n"
+        );
+    }
+
+    #[test]
+    fn unregistered_actor_types_yield_no_edge() {
+        let converter = Converter::new(mapping());
+        let protein = ixn(&[("exp", '+')], vec![actor("chemical", "MESH:D000001", 1, "A"), actor("protein", "PR:1", 2, "P")]);
+        assert_eq!(KgxExporter::new(&converter).convert(&protein).unwrap(), (Vec::new(), Vec::new()));
+        let converter = converter.with_strict_actor_types(true);
+        assert!(matches!(KgxExporter::new(&converter).convert(&protein), Err(CtdError::UnknownActorType { ixn_id: 1, .. })));
+    }
+}
@@ -1,4 +1,5 @@
 extern crate env_logger;
+#[macro_use]
 extern crate log;
 extern crate serde;
 extern crate serde_derive;
@@ -8,7 +9,24 @@ extern crate horned_owl;
 
 use std::collections;
 
+pub mod actor_type;
+pub mod association;
+pub mod config;
+pub mod converter;
+pub mod coverage;
+pub mod diff;
+pub mod error;
+pub mod filter;
+pub mod jsonl;
+pub mod kgx;
+pub mod mapping;
+pub mod merge;
 pub mod model;
+pub mod parser;
+pub mod rdf;
+pub mod tsv;
+pub mod validate;
+pub mod vocabulary;
 
 pub const OBO: &str = "http://purl.obolibrary.org/obo/";
 pub const CTDIXN: &str = "http://ctdbase.org/detail.go?type=relationship&ixnId=";
@@ -17,8 +35,15 @@ pub const MESH: &str = "http://id.nlm.nih.gov/mesh/";
 pub const CHEBI: &str = "http://purl.obolibrary.org/obo/CHEBI_";
 pub const NCBIGENE: &str = "http://identifiers.org/ncbigene:";
 pub const PMID: &str = "https://www.ncbi.nlm.nih.gov/pubmed";
+pub const OMIM: &str = "https://omim.org/entry/";
+pub const KEGG_PATHWAY: &str = "http://identifiers.org/kegg.pathway:";
+pub const REACTOME: &str = "http://identifiers.org/reactome:";
 pub const NCBI_TAXON: &str = "http://purl.obolibrary.org/obo/NCBITaxon_";
 pub const DC: &str = "http://purl.org/dc/elements/1.1/";
+/// Default namespace of the annotation properties minted here for CTD fields that no established vocabulary covers, such as `has_form` and `direct_evidence`.
+///
+/// They live next to the `CTDI_` interaction classes, under the OBO convention for an ontology's own properties, rather than on CTD's domain; see `Config::ctd_namespace`.
+pub const CTDI: &str = "http://purl.obolibrary.org/obo/ctdi#";
 
 lazy_static! {
     pub static ref ACTS_UPSTREAM_OF: String = format!("{}{}", OBO, "RO_0002263");
@@ -41,9 +66,20 @@ lazy_static! {
     pub static ref DC_SOURCE: String = format!("{}{}", DC, "source");
     pub static ref CHEMICAL_ENTITY: String = format!("{}{}", OBO, "CHEBI_24431");
     pub static ref GENE_ENTITY: String = format!("{}{}", OBO, "SO_0000704");
+    pub static ref GENE_PRODUCT_OF: String = format!("{}{}", OBO, "RO_0002204");
+    pub static ref IS_MARKER_FOR: String = format!("{}{}", OBO, "RO_0002607");
+    pub static ref IS_SUBSTANCE_THAT_TREATS: String = format!("{}{}", OBO, "RO_0002606");
+    pub static ref CORRELATED_WITH: String = format!("{}{}", OBO, "RO_0002610");
+    pub static ref PARTICIPATES_IN: String = format!("{}{}", OBO, "RO_0000056");
+    pub static ref ACTS_UPSTREAM_OF_OR_WITHIN: String = format!("{}{}", OBO, "RO_0002264");
+    pub static ref DEFINITION: String = format!("{}{}", OBO, "IAO_0000115");
+    pub static ref HAS_EXACT_SYNONYM: String = String::from("http://www.geneontology.org/formats/oboInOwl#hasExactSynonym");
+    pub static ref HAS_RELATED_SYNONYM: String = String::from("http://www.geneontology.org/formats/oboInOwl#hasRelatedSynonym");
+    pub static ref SKOS_EXACT_MATCH: String = String::from("http://www.w3.org/2004/02/skos/core#exactMatch");
+    pub static ref HAS_DB_XREF: String = String::from("http://www.geneontology.org/formats/oboInOwl#hasDbXref");
     pub static ref AXN_CODES: Vec<&'static str> = vec![
         "act", "pho", "exp", "myl", "sec", "loc", "clv", "mut", "deg", "spl", "rec", "sta", "met", "oxd", "ubq", "nit", "upt", "red", "alk", "sum", "gyc", "trt", "glc", "csy",
-        "upt", "red", "hdx"//, "abu", "ace", "oxd", "fol"
+        "hdx"
     ];
 }
 
@@ -84,3 +120,30 @@ pub fn get_class_map() -> collections::HashMap<String, String> {
     map.insert("hdx".to_string(), format!("{}{}", OBO, "CTDI_16"));
     map
 }
+
+/// Gene product class for each CTD gene form that denotes a product rather than the gene itself.
+pub fn get_form_map() -> collections::HashMap<String, String> {
+    let mut map = collections::HashMap::new();
+    map.insert("mRNA".to_string(), format!("{}{}", OBO, "SO_0000234"));
+    map.insert("protein".to_string(), format!("{}{}", OBO, "PR_000000001"));
+    map
+}
+
+pub fn get_prefix_mapping() -> curie::PrefixMapping {
+    let mut prefix_mapping = curie::PrefixMapping::default();
+    prefix_mapping.add_prefix("owl", "http://www.w3.org/2002/07/owl#").unwrap();
+    prefix_mapping.add_prefix("rdf", "http://www.w3.org/1999/02/22-rdf-syntax-ns#").unwrap();
+    prefix_mapping.add_prefix("xml", "http://www.w3.org/XML/1998/namespace").unwrap();
+    prefix_mapping.add_prefix("xsd", "http://www.w3.org/2001/XMLSchema#").unwrap();
+    prefix_mapping.add_prefix("rdfs", "http://www.w3.org/2000/01/rdf-schema#").unwrap();
+    prefix_mapping.add_prefix("CHEBI", CHEBI).unwrap();
+    prefix_mapping.add_prefix("PMID", PMID).unwrap();
+    prefix_mapping.add_prefix("MESH", MESH).unwrap();
+    prefix_mapping.add_prefix("NCBITaxon", NCBI_TAXON).unwrap();
+    prefix_mapping.add_prefix("OMIM", OMIM).unwrap();
+    prefix_mapping.add_prefix("KEGG", KEGG_PATHWAY).unwrap();
+    prefix_mapping.add_prefix("REACT", REACTOME).unwrap();
+    prefix_mapping.add_prefix("NCBIGENE", NCBIGENE).unwrap();
+    prefix_mapping.add_prefix("DC", DC).unwrap();
+    prefix_mapping
+}
@@ -0,0 +1,316 @@
+use itertools::Itertools;
+use std::collections;
+use std::error;
+use std::io;
+use std::path;
+use std::str;
+
+/// How many example ids are kept per kind of problem in a `MappingReport`.
+const EXAMPLE_LIMIT: usize = 5;
+
+/// SSSOM predicates that are read as a CHEBI–MESH equivalence; rows with any other predicate are skipped.
+const EQUIVALENCE_PREDICATES: [&str; 2] = ["skos:exactMatch", "owl:equivalentClass"];
+
+/// What to do with a MESH id that maps to more than one CHEBI id.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MappingPolicy {
+    /// Use the CHEBI id listed first.
+    #[default]
+    First,
+    /// Keep the MESH class, as if the id were unmapped.
+    SkipAmbiguous,
+    /// Keep the MESH class and state that it is equivalent to every CHEBI class.
+    All,
+}
+
+impl str::FromStr for MappingPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<MappingPolicy, String> {
+        match s {
+            "first" => Ok(MappingPolicy::First),
+            "skip-ambiguous" => Ok(MappingPolicy::SkipAmbiguous),
+            "all" => Ok(MappingPolicy::All),
+            _ => Err(format!("unknown mapping policy: {} (expected first, skip-ambiguous or all)", s)),
+        }
+    }
+}
+
+/// How the MESH class of a chemical is kept when it resolves to a CHEBI class.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MeshLink {
+    /// The MESH id is dropped.
+    #[default]
+    None,
+    /// The MESH class is declared equivalent to the CHEBI class.
+    Equivalent,
+    /// The CHEBI class gets a `skos:exactMatch` annotation with the MESH class IRI.
+    ExactMatch,
+    /// The CHEBI class gets an `oboInOwl:hasDbXref` annotation with the `MESH:` id.
+    Xref,
+}
+
+impl str::FromStr for MeshLink {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<MeshLink, String> {
+        match s {
+            "none" => Ok(MeshLink::None),
+            "equivalent" => Ok(MeshLink::Equivalent),
+            "exact-match" => Ok(MeshLink::ExactMatch),
+            "xref" => Ok(MeshLink::Xref),
+            _ => Err(format!("unknown mesh link: {} (expected none, equivalent, exact-match or xref)", s)),
+        }
+    }
+}
+
+/// The CHEBI class or classes a MESH chemical id resolves to under the mapping policy.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Resolution<'a> {
+    Unmapped,
+    Chebi(&'a str),
+    /// Ambiguous under `MappingPolicy::All`; the MESH class is kept and made equivalent to each of these.
+    Equivalents(&'a [String]),
+}
+
+/// Counts and examples of what the loader found, for logging.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MappingReport {
+    pub rows: usize,
+    pub duplicates: usize,
+    /// Rows that are neither a CHEBI–MESH pair nor a header, as `(line, content)`.
+    pub malformed: Vec<(usize, String)>,
+    pub skipped_predicates: collections::BTreeMap<String, usize>,
+    /// MESH ids mapping to several CHEBI ids.
+    pub ambiguous: Vec<String>,
+    /// CHEBI ids mapped from several MESH ids, which is harmless but worth knowing.
+    pub many_to_one: Vec<String>,
+}
+
+impl MappingReport {
+    /// Logs a summary with a few examples of each problem.
+    pub fn log(&self, mapping_path: &path::Path) {
+        info!("{:?}: {} mapping rows", mapping_path, self.rows);
+        if self.duplicates > 0 {
+            info!("{:?}: {} duplicate rows", mapping_path, self.duplicates);
+        }
+        if !self.malformed.is_empty() {
+            warn!(
+                "{:?}: {} malformed rows, e.g. {}",
+                mapping_path,
+                self.malformed.len(),
+                self.malformed.iter().take(EXAMPLE_LIMIT).map(|(line, row)| format!("line {}: {:?}", line, row)).join(", ")
+            );
+        }
+        for (predicate, count) in self.skipped_predicates.iter() {
+            info!("{:?}: skipped {} rows with predicate {}", mapping_path, count, predicate);
+        }
+        if !self.ambiguous.is_empty() {
+            warn!("{:?}: {} MESH ids map to several CHEBI ids, e.g. {}", mapping_path, self.ambiguous.len(), self.ambiguous.iter().take(EXAMPLE_LIMIT).join(", "));
+        }
+        if !self.many_to_one.is_empty() {
+            info!("{:?}: {} CHEBI ids are mapped from several MESH ids, e.g. {}", mapping_path, self.many_to_one.len(), self.many_to_one.iter().take(EXAMPLE_LIMIT).join(", "));
+        }
+    }
+}
+
+/// MESH to CHEBI id mapping, used to type chemical actors with CHEBI classes where one is known.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ChebiMeshMapping {
+    mesh_to_chebi: collections::HashMap<String, Vec<String>>,
+    policy: MappingPolicy,
+    mesh_link: MeshLink,
+}
+
+/// A map keyed by `MESH:` id with the `CHEBI:` id to use in its place, as built by earlier versions.
+impl From<collections::HashMap<String, String>> for ChebiMeshMapping {
+    fn from(chebi_to_mesh_map: collections::HashMap<String, String>) -> ChebiMeshMapping {
+        ChebiMeshMapping {
+            mesh_to_chebi: chebi_to_mesh_map.into_iter().map(|(mesh, chebi)| (mesh, vec![chebi])).collect(),
+            policy: MappingPolicy::First,
+            mesh_link: MeshLink::None,
+        }
+    }
+}
+
+impl ChebiMeshMapping {
+    /// Reads a two-column CHEBI/MESH TSV, in either column order, or an SSSOM TSV; the file may be gzip-compressed.
+    pub fn load(mapping_path: &path::Path, policy: MappingPolicy) -> Result<(ChebiMeshMapping, MappingReport), Box<dyn error::Error>> {
+        ChebiMeshMapping::read(crate::parser::open_input(mapping_path)?, policy)
+    }
+
+    /// Same as `load`, from an open reader.
+    ///
+    /// Blank lines and `#` lines (comments, or SSSOM metadata) are skipped. A first row without any `CHEBI:` or `MESH:` id is a header;
+    /// one with `subject_id`, `predicate_id` and `object_id` columns makes the rest of the file SSSOM.
+    pub fn read<R: io::BufRead>(input: R, policy: MappingPolicy) -> Result<(ChebiMeshMapping, MappingReport), Box<dyn error::Error>> {
+        let mut report = MappingReport::default();
+        let mut mesh_to_chebi: collections::HashMap<String, Vec<String>> = collections::HashMap::new();
+        let mut chebi_to_mesh: collections::HashMap<String, Vec<String>> = collections::HashMap::new();
+        let mut sssom_columns: Option<(usize, usize, usize)> = None;
+        let mut header_seen = false;
+        for (line_idx, line) in input.lines().enumerate() {
+            let line = line?;
+            let row = line.trim_end_matches('\r');
+            if row.trim().is_empty() || row.starts_with('#') {
+                continue;
+            }
+            let columns = row.split('\t').map(str::trim).collect_vec();
+            if !header_seen {
+                header_seen = true;
+                if !columns.iter().any(|c| normalize(c).is_some()) {
+                    let position = |name: &str| columns.iter().position(|c| *c == name);
+                    if let (Some(subject), Some(predicate), Some(object)) = (position("subject_id"), position("predicate_id"), position("object_id")) {
+                        sssom_columns = Some((subject, predicate, object));
+                    }
+                    continue;
+                }
+            }
+            let pair = match sssom_columns {
+                Some((subject, predicate, object)) => match (columns.get(subject), columns.get(predicate), columns.get(object)) {
+                    (Some(s), Some(p), Some(o)) if EQUIVALENCE_PREDICATES.contains(p) => chebi_mesh_pair(s, o),
+                    (Some(_), Some(p), Some(_)) => {
+                        *report.skipped_predicates.entry(p.to_string()).or_default() += 1;
+                        continue;
+                    }
+                    _ => None,
+                },
+                None if columns.len() == 2 => chebi_mesh_pair(columns[0], columns[1]),
+                None => None,
+            };
+            let (chebi, mesh) = match pair {
+                Some(pair) => pair,
+                None => {
+                    report.malformed.push((line_idx + 1, row.to_string()));
+                    continue;
+                }
+            };
+            report.rows += 1;
+            let chebis = mesh_to_chebi.entry(mesh.clone()).or_default();
+            if chebis.contains(&chebi) {
+                report.duplicates += 1;
+                continue;
+            }
+            chebis.push(chebi.clone());
+            chebi_to_mesh.entry(chebi).or_default().push(mesh);
+        }
+        report.ambiguous = mesh_to_chebi.iter().filter(|(_, chebis)| chebis.len() > 1).map(|(mesh, chebis)| format!("{} ({})", mesh, chebis.join(" "))).sorted().collect();
+        report.many_to_one = chebi_to_mesh.iter().filter(|(_, meshes)| meshes.len() > 1).map(|(chebi, meshes)| format!("{} ({})", chebi, meshes.join(" "))).sorted().collect();
+        Ok((ChebiMeshMapping { mesh_to_chebi, policy, mesh_link: MeshLink::None }, report))
+    }
+
+    /// Keeps the MESH class of mapped chemicals, linked to their CHEBI class as `mesh_link` says.
+    pub fn with_mesh_link(self, mesh_link: MeshLink) -> ChebiMeshMapping {
+        ChebiMeshMapping { mesh_link, ..self }
+    }
+
+    pub fn policy(&self) -> MappingPolicy {
+        self.policy
+    }
+
+    pub fn mesh_link(&self) -> MeshLink {
+        self.mesh_link
+    }
+
+    pub fn resolve(&self, mesh_id: &str) -> Resolution<'_> {
+        match (self.mesh_to_chebi.get(mesh_id).map(Vec::as_slice), self.policy) {
+            (None, _) | (Some([]), _) => Resolution::Unmapped,
+            (Some([chebi]), _) => Resolution::Chebi(chebi),
+            (Some([chebi, ..]), MappingPolicy::First) => Resolution::Chebi(chebi),
+            (Some(_), MappingPolicy::SkipAmbiguous) => Resolution::Unmapped,
+            (Some(chebis), MappingPolicy::All) => Resolution::Equivalents(chebis),
+        }
+    }
+}
+
+/// Normalizes a `CHEBI:` or `MESH:` id, whatever the case of its prefix, and tells which it is.
+fn normalize(id: &str) -> Option<(bool, String)> {
+    let (prefix, local) = id.split_once(':')?;
+    match prefix.to_ascii_uppercase().as_str() {
+        "CHEBI" if !local.is_empty() => Some((true, format!("CHEBI:{}", local))),
+        "MESH" if !local.is_empty() => Some((false, format!("MESH:{}", local))),
+        _ => None,
+    }
+}
+
+fn chebi_mesh_pair(a: &str, b: &str) -> Option<(String, String)> {
+    match (normalize(a)?, normalize(b)?) {
+        ((true, chebi), (false, mesh)) | ((false, mesh), (true, chebi)) => Some((chebi, mesh)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn read(input: &str, policy: MappingPolicy) -> (ChebiMeshMapping, MappingReport) {
+        ChebiMeshMapping::read(input.as_bytes(), policy).unwrap()
+    }
+
+    #[test]
+    fn reads_pairs_in_either_column_order_after_a_header() {
+        let (mapping, report) = read("# CHEBI to MESH\nchebi\tmesh\r\nCHEBI:1\tMESH:D000001\r\n\nmesh:D000002\tchebi:2\n", MappingPolicy::First);
+        assert_eq!(mapping.resolve("MESH:D000001"), Resolution::Chebi("CHEBI:1"));
+        assert_eq!(mapping.resolve("MESH:D000002"), Resolution::Chebi("CHEBI:2"));
+        assert_eq!(mapping.resolve("MESH:D000003"), Resolution::Unmapped);
+        assert_eq!(report.rows, 2);
+        assert!(report.malformed.is_empty());
+    }
+
+    #[test]
+    fn a_first_row_with_ids_is_not_a_header() {
+        let (mapping, report) = read("CHEBI:1\tMESH:D000001\n", MappingPolicy::First);
+        assert_eq!(mapping.resolve("MESH:D000001"), Resolution::Chebi("CHEBI:1"));
+        assert_eq!(report.rows, 1);
+    }
+
+    #[test]
+    fn reports_malformed_rows_by_line() {
+        let (_, report) = read("chebi\tmesh\nCHEBI:1\tCHEBI:2\nCHEBI:3\nCHEBI:4\tMESH:D000004\textra\nCHEBI:5\tMESH:D000005\n", MappingPolicy::First);
+        assert_eq!(report.rows, 1);
+        assert_eq!(report.malformed.iter().map(|(line, _)| *line).collect_vec(), vec![2, 3, 4]);
+    }
+
+    #[test]
+    fn reads_sssom_equivalences_and_skips_other_predicates() {
+        let input = "# curie_map:\n#   CHEBI: http://purl.obolibrary.org/obo/CHEBI_\n\
+                     subject_id\tsubject_label\tpredicate_id\tobject_id\tmapping_justification\n\
+                     MESH:D000001\ta\tskos:exactMatch\tCHEBI:1\tsemapv:ManualMappingCuration\n\
+                     CHEBI:2\tb\towl:equivalentClass\tMESH:D000002\tsemapv:ManualMappingCuration\n\
+                     MESH:D000003\tc\tskos:broadMatch\tCHEBI:3\tsemapv:ManualMappingCuration\n\
+                     MESH:D000004\td\tskos:broadMatch\tCHEBI:4\tsemapv:ManualMappingCuration\n\
+                     MESH:D000005\te\n";
+        let (mapping, report) = read(input, MappingPolicy::First);
+        assert_eq!(mapping.resolve("MESH:D000001"), Resolution::Chebi("CHEBI:1"));
+        assert_eq!(mapping.resolve("MESH:D000002"), Resolution::Chebi("CHEBI:2"));
+        assert_eq!(mapping.resolve("MESH:D000003"), Resolution::Unmapped);
+        assert_eq!(report.rows, 2);
+        assert_eq!(report.skipped_predicates.get("skos:broadMatch"), Some(&2));
+        assert_eq!(report.malformed, vec![(8, String::from("MESH:D000005\te"))]);
+    }
+
+    #[test]
+    fn counts_duplicates_once_and_reports_many_to_one() {
+        let (mapping, report) = read("CHEBI:1\tMESH:D000001\nCHEBI:1\tMESH:D000001\nCHEBI:1\tMESH:D000002\n", MappingPolicy::First);
+        assert_eq!(report.rows, 3);
+        assert_eq!(report.duplicates, 1);
+        assert!(report.ambiguous.is_empty());
+        assert_eq!(report.many_to_one, vec![String::from("CHEBI:1 (MESH:D000001 MESH:D000002)")]);
+        assert_eq!(mapping.resolve("MESH:D000002"), Resolution::Chebi("CHEBI:1"));
+    }
+
+    #[test]
+    fn resolves_ambiguous_ids_by_policy() {
+        let input = "CHEBI:1\tMESH:D000001\nCHEBI:2\tMESH:D000001\nCHEBI:3\tMESH:D000003\n";
+        let (first, report) = read(input, MappingPolicy::First);
+        assert_eq!(report.ambiguous, vec![String::from("MESH:D000001 (CHEBI:1 CHEBI:2)")]);
+        assert_eq!(first.resolve("MESH:D000001"), Resolution::Chebi("CHEBI:1"));
+        let (skip, _) = read(input, MappingPolicy::SkipAmbiguous);
+        assert_eq!(skip.resolve("MESH:D000001"), Resolution::Unmapped);
+        assert_eq!(skip.resolve("MESH:D000003"), Resolution::Chebi("CHEBI:3"));
+        let (all, _) = read(input, MappingPolicy::All);
+        assert_eq!(all.resolve("MESH:D000001"), Resolution::Equivalents(&[String::from("CHEBI:1"), String::from("CHEBI:2")]));
+        assert_eq!(all.resolve("MESH:D000003"), Resolution::Chebi("CHEBI:3"));
+    }
+}
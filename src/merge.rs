@@ -0,0 +1,202 @@
+//! Single documents merged from chunks that were converted in parallel.
+//!
+//! horned-owl ontologies are `Rc` based and cannot leave the thread that built them, so each worker serializes its chunk into a `Chunk`,
+//! and the merged document is written from the chunks in order, leaving out what an earlier chunk already stated.
+
+use crate::rdf::{Triple, TripleBuilder, TurtlePrefixes};
+use horned_owl::io::owx;
+use horned_owl::ontology::axiom_mapped::AxiomMappedOntology;
+use itertools::Itertools;
+use quick_xml::events::Event;
+use std::collections;
+use std::error;
+use std::io;
+
+/// A converted chunk, in a form that can be sent back to the thread writing the merged document.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Chunk {
+    /// The top-level elements of the chunk's OWL/XML document, each an axiom, as written.
+    Owx(Vec<Vec<u8>>),
+    Triples(Vec<Triple>),
+}
+
+impl Chunk {
+    pub fn owx(ontology: &AxiomMappedOntology, prefix_mapping: &curie::PrefixMapping) -> Result<Chunk, Box<dyn error::Error>> {
+        let mut document = Vec::new();
+        owx::writer::write(&mut document, ontology, Some(prefix_mapping))?;
+        Ok(Chunk::Owx(owx_elements(&document)?.into_iter().filter(|element| !element.starts_with(b"<Prefix")).map(<[u8]>::to_vec).collect()))
+    }
+
+    /// `blank_node_prefix` must differ between the chunks of a document.
+    pub fn triples(ontology: &AxiomMappedOntology, blank_node_prefix: &str) -> Result<Chunk, Box<dyn error::Error>> {
+        let mut triple_builder = TripleBuilder::for_chunk(ontology, blank_node_prefix);
+        let mut triples = Vec::new();
+        for annotated_axiom in ontology.i().iter() {
+            triples.append(&mut triple_builder.axiom_triples(annotated_axiom)?);
+        }
+        Ok(Chunk::Triples(triples))
+    }
+}
+
+/// The top-level elements of the `<Ontology>` element of an OWL/XML document, each with its content, as slices of `document`.
+fn owx_elements(document: &[u8]) -> Result<Vec<&[u8]>, Box<dyn error::Error>> {
+    let mut reader = quick_xml::Reader::from_reader(document);
+    let mut buf = Vec::new();
+    let mut elements = Vec::new();
+    let (mut depth, mut start) = (0, 0);
+    loop {
+        let position = reader.buffer_position();
+        buf.clear();
+        match reader.read_event_into(&mut buf)? {
+            Event::Start(_) => {
+                if depth == 1 {
+                    start = position;
+                }
+                depth += 1;
+            }
+            Event::End(_) => {
+                depth -= 1;
+                if depth == 1 {
+                    elements.push(&document[start..reader.buffer_position()]);
+                }
+            }
+            Event::Empty(_) if depth == 1 => elements.push(&document[position..reader.buffer_position()]),
+            Event::Eof => return Ok(elements),
+            _ => {}
+        }
+    }
+}
+
+/// Writes `header`, with its prefixes, and the axioms of every chunk as one OWL/XML document.
+pub fn write_owx<W: io::Write, I: IntoIterator<Item = Chunk>>(
+    write: &mut W,
+    header: &AxiomMappedOntology,
+    prefix_mapping: &curie::PrefixMapping,
+    chunks: I,
+) -> Result<(), Box<dyn error::Error>> {
+    let mut document = Vec::new();
+    owx::writer::write(&mut document, header, Some(prefix_mapping))?;
+    let end = document.windows(b"</Ontology>".len()).rposition(|w| w == b"</Ontology>").ok_or("no </Ontology> in the owx header")?;
+    write.write_all(&document[..end])?;
+    let mut seen: collections::HashSet<Vec<u8>> = owx_elements(&document)?.into_iter().map(<[u8]>::to_vec).collect();
+    for chunk in chunks {
+        let elements = match chunk {
+            Chunk::Owx(elements) => elements,
+            Chunk::Triples(_) => return Err("cannot write triples as owx".into()),
+        };
+        for element in elements {
+            if seen.contains(&element) {
+                continue;
+            }
+            write.write_all(b"    ")?;
+            write.write_all(&element)?;
+            write.write_all(b"\n")?;
+            seen.insert(element);
+        }
+    }
+    write.write_all(&document[end..])?;
+    Ok(())
+}
+
+/// The triples of `header` and of every chunk, each once.
+fn merged_triples<I: IntoIterator<Item = Chunk>>(triple_builder: &mut TripleBuilder, header: &AxiomMappedOntology, chunks: I) -> Result<Vec<Triple>, Box<dyn error::Error>> {
+    let mut triples = triple_builder.ontology_triples(header)?;
+    for chunk in chunks {
+        match chunk {
+            Chunk::Triples(mut chunk_triples) => triples.append(&mut chunk_triples),
+            Chunk::Owx(_) => return Err("cannot write owx as triples".into()),
+        }
+    }
+    Ok(triples.into_iter().unique().collect())
+}
+
+pub fn write_ntriples<W: io::Write, I: IntoIterator<Item = Chunk>>(write: &mut W, header: &AxiomMappedOntology, chunks: I) -> Result<(), Box<dyn error::Error>> {
+    for triple in merged_triples(&mut TripleBuilder::new(header), header, chunks)? {
+        writeln!(write, "{} {} {} .", triple.subject, triple.predicate, triple.object)?;
+    }
+    Ok(())
+}
+
+pub fn write_turtle<W: io::Write, I: IntoIterator<Item = Chunk>>(
+    write: &mut W,
+    header: &AxiomMappedOntology,
+    prefix_mapping: &curie::PrefixMapping,
+    chunks: I,
+) -> Result<(), Box<dyn error::Error>> {
+    let mut triple_builder = TripleBuilder::new(header);
+    let triples = merged_triples(&mut triple_builder, header, chunks)?;
+    let prefixes = TurtlePrefixes::new(prefix_mapping);
+    prefixes.write_header(write)?;
+    crate::rdf::write_turtle_blocks(write, &prefixes, triple_builder.ontology(), &triples, "")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use horned_owl::model::*;
+
+    fn header(build: &Build) -> AxiomMappedOntology {
+        let mut header = AxiomMappedOntology::default();
+        let provided_by = build.annotation_property("http://purl.org/pav/providedBy");
+        header.insert(Axiom::DeclareAnnotationProperty(DeclareAnnotationProperty(provided_by.clone())));
+        header.insert(Axiom::OntologyAnnotation(OntologyAnnotation(Annotation { ap: provided_by, av: AnnotationValue::IRI(build.iri("http://ctdbase.org")) })));
+        header
+    }
+
+    /// Interaction `id` typed as a CHEBI class; every chunk declares the same class.
+    fn chunk_ontology(build: &Build, id: i32) -> AxiomMappedOntology {
+        let mut ontology = AxiomMappedOntology::default();
+        let chebi = build.class(format!("{}1", crate::CHEBI));
+        let ixn = build.named_individual(format!("{}{}", crate::CTDIXN, id));
+        ontology.insert(Axiom::DeclareClass(DeclareClass(chebi.clone())));
+        ontology.insert(Axiom::ClassAssertion(ClassAssertion {
+            ce: ClassExpression::ObjectSomeValuesFrom { ope: build.object_property(format!("{}RO_0002233", crate::OBO)).into(), bce: Box::new(chebi.into()) },
+            i: ixn,
+        }));
+        ontology
+    }
+
+    #[test]
+    fn owx_elements_are_the_top_level_children_of_the_ontology() {
+        let document = b"<?xml version=\"1.0\"?>\n<Ontology>\n    <Prefix name=\"a\" IRI=\"http://a/\"/>\n    <Declaration>\n        <Class IRI=\"http://a/b\"/>\n    </Declaration>\n</Ontology>\n";
+        assert_eq!(
+            owx_elements(document).unwrap(),
+            vec![&b"<Prefix name=\"a\" IRI=\"http://a/\"/>"[..], &b"<Declaration>\n        <Class IRI=\"http://a/b\"/>\n    </Declaration>"[..]]
+        );
+    }
+
+    #[test]
+    fn owx_chunks_are_merged_after_the_header_once() {
+        let build = Build::new();
+        let prefix_mapping = crate::get_prefix_mapping();
+        let chunks = vec![Chunk::owx(&chunk_ontology(&build, 1), &prefix_mapping).unwrap(), Chunk::owx(&chunk_ontology(&build, 2), &prefix_mapping).unwrap()];
+        let mut out = Vec::new();
+        write_owx(&mut out, &header(&build), &prefix_mapping, chunks).unwrap();
+        let merged = String::from_utf8(out).unwrap();
+        assert_eq!(merged.matches("<Prefix name=\"CHEBI\"").count(), 1);
+        assert_eq!(merged.matches("<Declaration>\n        <Class abbreviatedIRI=\"CHEBI:1\"/>").count(), 1);
+        assert_eq!(merged.matches("<ClassAssertion>").count(), 2);
+        assert!(merged.find("http://ctdbase.org</IRI>").unwrap() < merged.find("<ClassAssertion>").unwrap());
+        assert!(merged.trim_end().ends_with("</Ontology>"));
+    }
+
+    #[test]
+    fn triple_chunks_keep_blank_nodes_apart() {
+        let build = Build::new();
+        let chunks = vec![Chunk::triples(&chunk_ontology(&build, 1), "c0").unwrap(), Chunk::triples(&chunk_ontology(&build, 2), "c1").unwrap()];
+        let mut out = Vec::new();
+        write_ntriples(&mut out, &header(&build), chunks).unwrap();
+        let merged = String::from_utf8(out).unwrap();
+        assert!(merged.starts_with("_:ontology <http://www.w3.org/1999/02/22-rdf-syntax-ns#type> <http://www.w3.org/2002/07/owl#Ontology> ."));
+        assert_eq!(merged.matches(&format!("<{}1> <http://www.w3.org/1999/02/22-rdf-syntax-ns#type> <http://www.w3.org/2002/07/owl#Class> .", crate::CHEBI)).count(), 1);
+        assert!(merged.contains("_:c0b1 <http://www.w3.org/2002/07/owl#onProperty>"));
+        assert!(merged.contains("_:c1b1 <http://www.w3.org/2002/07/owl#onProperty>"));
+    }
+
+    #[test]
+    fn chunks_must_match_the_document_format() {
+        let build = Build::new();
+        let triples = Chunk::triples(&chunk_ontology(&build, 1), "c0").unwrap();
+        assert!(write_owx(&mut Vec::new(), &header(&build), &crate::get_prefix_mapping(), vec![triples]).is_err());
+    }
+}
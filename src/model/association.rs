@@ -0,0 +1,325 @@
+use crate::tsv;
+use crate::tsv::TsvRecord;
+
+/// The kinds of curated evidence CTD gives for a direct association with a disease.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum DirectEvidence {
+    /// The entity correlates with the disease or plays a role in its etiology.
+    MarkerMechanism,
+    /// The entity has a known or potential role in treating the disease.
+    Therapeutic,
+}
+
+impl DirectEvidence {
+    pub fn from_code(code: &str) -> Option<DirectEvidence> {
+        match code {
+            "marker/mechanism" => Some(DirectEvidence::MarkerMechanism),
+            "therapeutic" => Some(DirectEvidence::Therapeutic),
+            _ => None,
+        }
+    }
+
+    pub fn code(&self) -> &'static str {
+        match self {
+            DirectEvidence::MarkerMechanism => "marker/mechanism",
+            DirectEvidence::Therapeutic => "therapeutic",
+        }
+    }
+}
+
+/// A row of `CTD_chemicals_diseases.tsv`: either curated, with direct evidence, or inferred through a gene both are curated with.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChemicalDisease {
+    pub chemical_name: String,
+    /// The `MESH:` id, as chemical actors have it; the table leaves out the prefix.
+    pub chemical_id: String,
+    pub cas_rn: Option<String>,
+    pub disease_name: String,
+    /// A `MESH:` or `OMIM:` id.
+    pub disease_id: String,
+    pub direct_evidence: Vec<DirectEvidence>,
+    pub inference_gene_symbol: Option<String>,
+    pub inference_score: Option<f64>,
+    pub omim_ids: Vec<String>,
+    pub pm_ids: Vec<i32>,
+}
+
+impl ChemicalDisease {
+    pub fn is_curated(&self) -> bool {
+        !self.direct_evidence.is_empty()
+    }
+}
+
+impl TsvRecord for ChemicalDisease {
+    const COLUMNS: &'static [&'static str] =
+        &["ChemicalName", "ChemicalID", "CasRN", "DiseaseName", "DiseaseID", "DirectEvidence", "InferenceGeneSymbol", "InferenceScore", "OmimIDs", "PubMedIDs"];
+
+    fn from_columns(columns: &[&str]) -> Result<ChemicalDisease, String> {
+        let association = ChemicalDisease {
+            chemical_name: columns[0].trim().to_string(),
+            chemical_id: tsv::prefixed_id(columns[1], "MESH:", "ChemicalID")?,
+            cas_rn: tsv::optional(columns[2]),
+            disease_name: columns[3].trim().to_string(),
+            disease_id: disease_id(columns[4])?,
+            direct_evidence: direct_evidence(columns[5])?,
+            inference_gene_symbol: tsv::optional(columns[6]),
+            inference_score: tsv::optional_number(columns[7], "InferenceScore")?,
+            omim_ids: tsv::list(columns[8]),
+            pm_ids: tsv::pm_ids(columns[9])?,
+        };
+        match (association.is_curated(), &association.inference_gene_symbol) {
+            (true, Some(_)) => Err(String::from("has both DirectEvidence and InferenceGeneSymbol")),
+            (false, None) => Err(String::from("has neither DirectEvidence nor InferenceGeneSymbol")),
+            _ => Ok(association),
+        }
+    }
+}
+
+/// A row of `CTD_genes_diseases.tsv`: either curated, with direct evidence, or inferred through a chemical both are curated with.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GeneDisease {
+    pub gene_symbol: String,
+    /// The `GENE:` id, as gene actors have it; the table leaves out the prefix.
+    pub gene_id: String,
+    pub disease_name: String,
+    /// A `MESH:` or `OMIM:` id.
+    pub disease_id: String,
+    pub direct_evidence: Vec<DirectEvidence>,
+    pub inference_chemical_name: Option<String>,
+    pub inference_score: Option<f64>,
+    pub omim_ids: Vec<String>,
+    pub pm_ids: Vec<i32>,
+}
+
+impl GeneDisease {
+    pub fn is_curated(&self) -> bool {
+        !self.direct_evidence.is_empty()
+    }
+}
+
+impl TsvRecord for GeneDisease {
+    const COLUMNS: &'static [&'static str] =
+        &["GeneSymbol", "GeneID", "DiseaseName", "DiseaseID", "DirectEvidence", "InferenceChemicalName", "InferenceScore", "OmimIDs", "PubMedIDs"];
+
+    fn from_columns(columns: &[&str]) -> Result<GeneDisease, String> {
+        let association = GeneDisease {
+            gene_symbol: columns[0].trim().to_string(),
+            gene_id: tsv::prefixed_id(columns[1], "GENE:", "GeneID")?,
+            disease_name: columns[2].trim().to_string(),
+            disease_id: disease_id(columns[3])?,
+            direct_evidence: direct_evidence(columns[4])?,
+            inference_chemical_name: tsv::optional(columns[5]),
+            inference_score: tsv::optional_number(columns[6], "InferenceScore")?,
+            omim_ids: tsv::list(columns[7]),
+            pm_ids: tsv::pm_ids(columns[8])?,
+        };
+        match (association.is_curated(), &association.inference_chemical_name) {
+            (true, Some(_)) => Err(String::from("has both DirectEvidence and InferenceChemicalName")),
+            (false, None) => Err(String::from("has neither DirectEvidence nor InferenceChemicalName")),
+            _ => Ok(association),
+        }
+    }
+}
+
+/// A row of `CTD_chem_pathways_enriched.tsv`: a pathway enriched among the genes a chemical interacts with.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChemicalPathway {
+    pub chemical_name: String,
+    /// The `MESH:` id, as chemical actors have it; the table leaves out the prefix.
+    pub chemical_id: String,
+    pub cas_rn: Option<String>,
+    pub pathway_name: String,
+    /// A `KEGG:` or `REACT:` id.
+    pub pathway_id: String,
+    pub p_value: f64,
+    /// The p-value corrected for multiple testing.
+    pub corrected_p_value: f64,
+    /// Genes the chemical interacts with that are in the pathway, of all genes it interacts with.
+    pub target_match_qty: u32,
+    pub target_total_qty: u32,
+    /// Genes in the pathway, of all annotated genes.
+    pub background_match_qty: u32,
+    pub background_total_qty: u32,
+}
+
+impl TsvRecord for ChemicalPathway {
+    const COLUMNS: &'static [&'static str] = &[
+        "ChemicalName",
+        "ChemicalID",
+        "CasRN",
+        "PathwayName",
+        "PathwayID",
+        "PValue",
+        "CorrectedPValue",
+        "TargetMatchQty",
+        "TargetTotalQty",
+        "BackgroundMatchQty",
+        "BackgroundTotalQty",
+    ];
+
+    fn from_columns(columns: &[&str]) -> Result<ChemicalPathway, String> {
+        Ok(ChemicalPathway {
+            chemical_name: columns[0].trim().to_string(),
+            chemical_id: tsv::prefixed_id(columns[1], "MESH:", "ChemicalID")?,
+            cas_rn: tsv::optional(columns[2]),
+            pathway_name: columns[3].trim().to_string(),
+            pathway_id: pathway_id(columns[4])?,
+            p_value: tsv::number(columns[5], "PValue")?,
+            corrected_p_value: tsv::number(columns[6], "CorrectedPValue")?,
+            target_match_qty: tsv::number(columns[7], "TargetMatchQty")?,
+            target_total_qty: tsv::number(columns[8], "TargetTotalQty")?,
+            background_match_qty: tsv::number(columns[9], "BackgroundMatchQty")?,
+            background_total_qty: tsv::number(columns[10], "BackgroundTotalQty")?,
+        })
+    }
+}
+
+/// A row of `CTD_genes_pathways.tsv`: a gene annotated to a pathway.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GenePathway {
+    pub gene_symbol: String,
+    /// The `GENE:` id, as gene actors have it; the table leaves out the prefix.
+    pub gene_id: String,
+    pub pathway_name: String,
+    /// A `KEGG:` or `REACT:` id.
+    pub pathway_id: String,
+}
+
+impl TsvRecord for GenePathway {
+    const COLUMNS: &'static [&'static str] = &["GeneSymbol", "GeneID", "PathwayName", "PathwayID"];
+
+    fn from_columns(columns: &[&str]) -> Result<GenePathway, String> {
+        Ok(GenePathway {
+            gene_symbol: columns[0].trim().to_string(),
+            gene_id: tsv::prefixed_id(columns[1], "GENE:", "GeneID")?,
+            pathway_name: columns[2].trim().to_string(),
+            pathway_id: pathway_id(columns[3])?,
+        })
+    }
+}
+
+fn disease_id(column: &str) -> Result<String, String> {
+    match column.trim() {
+        id if id.starts_with("MESH:") || id.starts_with("OMIM:") => Ok(id.to_string()),
+        id => Err(format!("DiseaseID is neither a MESH nor an OMIM id: {:?}", id)),
+    }
+}
+
+fn pathway_id(column: &str) -> Result<String, String> {
+    match column.trim() {
+        id if id.starts_with("KEGG:") || id.starts_with("REACT:") => Ok(id.to_string()),
+        id => Err(format!("PathwayID is neither a KEGG nor a Reactome id: {:?}", id)),
+    }
+}
+
+fn direct_evidence(column: &str) -> Result<Vec<DirectEvidence>, String> {
+    tsv::list(column).iter().map(|code| DirectEvidence::from_code(code).ok_or_else(|| format!("unknown DirectEvidence {:?}", code))).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::CtdError;
+    use crate::tsv::TsvReader;
+
+    fn read<T: TsvRecord>(input: &str) -> Vec<Result<T, CtdError>> {
+        TsvReader::<_, T>::new(input.as_bytes()).collect()
+    }
+
+    #[test]
+    fn chemical_disease_rows() {
+        let input = "# Fields:\n# ChemicalName\tChemicalID\tCasRN\tDiseaseName\tDiseaseID\tDirectEvidence\tInferenceGeneSymbol\tInferenceScore\tOmimIDs\tPubMedIDs\n#\n\
+            Calcimycin\tD000001\t52665-69-7\tLung Neoplasms\tMESH:D008175\tmarker/mechanism|therapeutic\t\t\t\t1|2\n\
+            Calcimycin\tD000001\t\tBreast Neoplasms\tOMIM:114480\t\tCYP1B1\t4.5\t114480|211410\t3\n";
+        let rows = read::<ChemicalDisease>(input).into_iter().collect::<Result<Vec<_>, _>>().unwrap();
+        assert_eq!(
+            rows[0],
+            ChemicalDisease {
+                chemical_name: String::from("Calcimycin"),
+                chemical_id: String::from("MESH:D000001"),
+                cas_rn: Some(String::from("52665-69-7")),
+                disease_name: String::from("Lung Neoplasms"),
+                disease_id: String::from("MESH:D008175"),
+                direct_evidence: vec![DirectEvidence::MarkerMechanism, DirectEvidence::Therapeutic],
+                inference_gene_symbol: None,
+                inference_score: None,
+                omim_ids: Vec::new(),
+                pm_ids: vec![1, 2],
+            }
+        );
+        assert!(rows[0].is_curated() && !rows[1].is_curated());
+        assert_eq!((rows[1].cas_rn.as_deref(), rows[1].disease_id.as_str()), (None, "OMIM:114480"));
+        assert_eq!((rows[1].inference_gene_symbol.as_deref(), rows[1].inference_score), (Some("CYP1B1"), Some(4.5)));
+        assert_eq!(rows[1].omim_ids, vec!["114480", "211410"]);
+    }
+
+    #[test]
+    fn chemical_disease_rows_are_either_curated_or_inferred() {
+        let rows = read::<ChemicalDisease>(
+            "A\tD1\t\tB\tMESH:D2\ttherapeutic\tCYP1B1\t1\t\t1\nA\tD1\t\tB\tMESH:D2\t\t\t\t\t1\nA\tD1\t\tB\tMESH:D2\tcausal\t\t\t\t1\nA\tD1\t\tB\tDOID:1\ttherapeutic\t\t\t\t1\n",
+        );
+        let messages = rows.into_iter().map(|row| row.unwrap_err().to_string()).collect::<Vec<_>>();
+        assert_eq!(
+            messages,
+            vec![
+                "line 1: has both DirectEvidence and InferenceGeneSymbol",
+                "line 2: has neither DirectEvidence nor InferenceGeneSymbol",
+                "line 3: unknown DirectEvidence \"causal\"",
+                "line 4: DiseaseID is neither a MESH nor an OMIM id: \"DOID:1\"",
+            ]
+        );
+    }
+
+    #[test]
+    fn gene_disease_rows() {
+        let input = "# GeneSymbol\tGeneID\tDiseaseName\tDiseaseID\tDirectEvidence\tInferenceChemicalName\tInferenceScore\tOmimIDs\tPubMedIDs\n\
+            CYP1B1\t1545\tGlaucoma\tMESH:D005901\tmarker/mechanism\t\t\t231300\t5|6\n\
+            CYP1B1\tGENE:1545\tBreast Neoplasms\tMESH:D001943\t\tCalcimycin\t\t\t\n\
+            CYP1B1\t1545\tGlaucoma\tMESH:D005901\t\t\t\t\t\n";
+        let rows = read::<GeneDisease>(input);
+        let curated = rows[0].as_ref().unwrap();
+        assert_eq!((curated.gene_id.as_str(), curated.direct_evidence.as_slice()), ("GENE:1545", [DirectEvidence::MarkerMechanism].as_slice()));
+        assert_eq!((curated.omim_ids.as_slice(), curated.pm_ids.as_slice()), ([String::from("231300")].as_slice(), [5, 6].as_slice()));
+        let inferred = rows[1].as_ref().unwrap();
+        assert_eq!((inferred.gene_id.as_str(), inferred.inference_chemical_name.as_deref(), inferred.inference_score), ("GENE:1545", Some("Calcimycin"), None));
+        assert!(inferred.pm_ids.is_empty());
+        assert_eq!(rows[2], Err(CtdError::Tsv { line: 4, message: String::from("has neither DirectEvidence nor InferenceChemicalName") }));
+    }
+
+    #[test]
+    fn chemical_pathway_rows() {
+        let rows = read::<ChemicalPathway>("Calcimycin\tD000001\t52665-69-7\tApoptosis\tKEGG:hsa04210\t1e-5\t0.01\t3\t40\t90\t20000\nA\tD1\t\tP\tGO:1\t1\t1\t1\t1\t1\t1\n");
+        assert_eq!(
+            rows[0],
+            Ok(ChemicalPathway {
+                chemical_name: String::from("Calcimycin"),
+                chemical_id: String::from("MESH:D000001"),
+                cas_rn: Some(String::from("52665-69-7")),
+                pathway_name: String::from("Apoptosis"),
+                pathway_id: String::from("KEGG:hsa04210"),
+                p_value: 1e-5,
+                corrected_p_value: 0.01,
+                target_match_qty: 3,
+                target_total_qty: 40,
+                background_match_qty: 90,
+                background_total_qty: 20000,
+            })
+        );
+        assert_eq!(rows[1], Err(CtdError::Tsv { line: 2, message: String::from("PathwayID is neither a KEGG nor a Reactome id: \"GO:1\"") }));
+    }
+
+    #[test]
+    fn gene_pathway_rows() {
+        let rows = read::<GenePathway>("# Fields:\nCYP1B1\t1545\tMetabolism\tREACT:R-HSA-1430728\nCYP1B1\t1545\tMetabolism\n");
+        assert_eq!(
+            rows[0],
+            Ok(GenePathway {
+                gene_symbol: String::from("CYP1B1"),
+                gene_id: String::from("GENE:1545"),
+                pathway_name: String::from("Metabolism"),
+                pathway_id: String::from("REACT:R-HSA-1430728")
+            })
+        );
+        assert!(matches!(rows[1], Err(CtdError::Tsv { line: 3, .. })));
+    }
+}
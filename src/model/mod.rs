@@ -1,9 +1,20 @@
+//! The CTD interaction model, deserialized from the structured XML and read from or written to JSON Lines, and the rows of CTD's association and vocabulary tables.
+//!
+//! Field names are the JSON ones; the `alias`es are the XML names as quick-xml presents them, `@` for attributes and `$text` for element text.
+
 use itertools::Itertools;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
+
+mod association;
+mod vocabulary;
+pub use self::association::*;
+pub use self::vocabulary::*;
 
-#[derive(Deserialize, Debug, PartialEq, Clone)]
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
 pub struct Taxon {
+    #[serde(alias = "@id")]
     pub id: i32,
+    #[serde(alias = "$text")]
     pub text: String,
 }
 
@@ -13,9 +24,9 @@ impl Taxon {
     }
 }
 
-#[derive(Deserialize, Debug, PartialEq, Clone)]
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
 pub struct Reference {
-    #[serde(rename(deserialize = "pmid"))]
+    #[serde(rename = "pmid", alias = "@pmid")]
     pub pm_id: i32,
 }
 
@@ -25,14 +36,17 @@ impl Reference {
     }
 }
 
-#[derive(Deserialize, Debug, PartialEq, Clone)]
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
 pub struct AXN {
+    #[serde(alias = "@code")]
     pub code: String,
-    #[serde(rename(deserialize = "degreecode"))]
+    #[serde(rename = "degreecode", alias = "@degreecode")]
     pub degree_code: char,
+    #[serde(alias = "@position")]
     pub position: i8,
-    #[serde(rename(deserialize = "parentid"))]
+    #[serde(rename = "parentid", alias = "@parentid")]
     pub parent_id: i32,
+    #[serde(alias = "$text")]
     pub text: String,
 }
 
@@ -40,25 +54,61 @@ impl AXN {
     pub fn new(code: String, degree_code: char, position: i8, parent_id: i32, text: String) -> AXN {
         AXN { code, degree_code, position, parent_id, text }
     }
+
+    pub fn degree(&self) -> Degree {
+        Degree::from_code(self.degree_code)
+    }
+}
+
+/// CTD degree codes, i.e. how an action changes its target.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Degree {
+    /// `+`
+    Increases,
+    /// `-`
+    Decreases,
+    /// `1` and `^`, an effect of unspecified direction
+    Affects,
+    /// `0`
+    NoEffect,
+    Unknown(char),
+}
+
+impl Degree {
+    pub fn from_code(code: char) -> Degree {
+        match code {
+            '+' => Degree::Increases,
+            '-' => Degree::Decreases,
+            '1' | '^' => Degree::Affects,
+            '0' => Degree::NoEffect,
+            _ => Degree::Unknown(code),
+        }
+    }
 }
 
-#[derive(Deserialize, Debug, PartialEq, Clone)]
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
 pub struct Actor {
-    #[serde(rename(deserialize = "type"))]
+    #[serde(rename = "type", alias = "@type")]
     pub actor_type: String,
+    #[serde(alias = "@id")]
     pub id: String,
+    #[serde(alias = "@position")]
     pub position: i8,
-    #[serde(rename(deserialize = "parentid"))]
+    #[serde(rename = "parentid", alias = "@parentid")]
     pub parent_id: i32,
+    #[serde(alias = "@form")]
     pub form: Option<String>,
+    #[serde(alias = "@formqualifier")]
     pub form_qualifier: Option<String>,
+    #[serde(alias = "@seqid")]
     pub seq_id: Option<String>,
+    /// Only atomic actors have text; an `ixn` actor has nested axns and actors instead.
+    #[serde(alias = "$text")]
     pub text: Option<String>,
-    #[serde(rename(deserialize = "axn"))]
+    #[serde(alias = "axn", default)]
     pub axns: Vec<AXN>,
+    #[serde(alias = "actor", default)]
     pub actors: Vec<Actor>,
-    // #[serde(rename(deserialize = "actor"))]
-    // pub nested_actors: Option<Vec<Actor>>,
 }
 
 impl Actor {
@@ -70,12 +120,17 @@ impl Actor {
     }
 }
 
-#[derive(Deserialize, Debug, PartialEq, Clone)]
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Default)]
 pub struct IXN {
+    #[serde(alias = "@id")]
     pub id: i32,
+    #[serde(default)]
     pub taxon: Vec<Taxon>,
+    #[serde(default)]
     pub reference: Vec<Reference>,
+    #[serde(alias = "axn", default)]
     pub axns: Vec<AXN>,
+    #[serde(alias = "actor", default)]
     pub actors: Vec<Actor>,
 }
 
@@ -85,9 +140,9 @@ impl IXN {
     }
 }
 
-#[derive(Deserialize, Debug, PartialEq, Clone)]
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Default)]
 pub struct IXNSet {
-    #[serde(rename(deserialize = "ixn"))]
+    #[serde(rename = "ixn", default)]
     pub ixns: Vec<IXN>,
 }
 
@@ -0,0 +1,127 @@
+use crate::tsv;
+use crate::tsv::TsvRecord;
+
+/// A row of `CTD_chemicals.tsv`, CTD's chemical vocabulary taken from the MeSH Supplementary and Descriptor records.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChemicalTerm {
+    pub name: String,
+    /// The `MESH:` id, as chemical actors have it.
+    pub id: String,
+    pub cas_rn: Option<String>,
+    pub definition: Option<String>,
+    /// `MESH:` ids of the broader chemicals.
+    pub parent_ids: Vec<String>,
+    pub tree_numbers: Vec<String>,
+    pub parent_tree_numbers: Vec<String>,
+    pub synonyms: Vec<String>,
+}
+
+impl TsvRecord for ChemicalTerm {
+    const COLUMNS: &'static [&'static str] = &["ChemicalName", "ChemicalID", "CasRN", "Definition", "ParentIDs", "TreeNumbers", "ParentTreeNumbers", "Synonyms"];
+
+    fn from_columns(columns: &[&str]) -> Result<ChemicalTerm, String> {
+        Ok(ChemicalTerm {
+            name: columns[0].trim().to_string(),
+            id: tsv::prefixed_id(columns[1], "MESH:", "ChemicalID")?,
+            cas_rn: tsv::optional(columns[2]),
+            definition: tsv::optional(columns[3]),
+            parent_ids: tsv::list(columns[4]).iter().map(|id| tsv::prefixed_id(id, "MESH:", "ParentIDs")).collect::<Result<_, _>>()?,
+            tree_numbers: tsv::list(columns[5]),
+            parent_tree_numbers: tsv::list(columns[6]),
+            synonyms: tsv::list(columns[7]),
+        })
+    }
+}
+
+/// A row of `CTD_genes.tsv`, CTD's gene vocabulary taken from NCBI Gene.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GeneTerm {
+    /// The official symbol.
+    pub symbol: String,
+    pub name: String,
+    /// The `GENE:` id, as gene actors have it; the table leaves out the prefix.
+    pub id: String,
+    pub alt_gene_ids: Vec<String>,
+    /// Alternative symbols.
+    pub synonyms: Vec<String>,
+    pub biogrid_ids: Vec<String>,
+    pub pharmgkb_ids: Vec<String>,
+    pub uniprot_ids: Vec<String>,
+}
+
+impl TsvRecord for GeneTerm {
+    const COLUMNS: &'static [&'static str] = &["GeneSymbol", "GeneName", "GeneID", "AltGeneIDs", "Synonyms", "BioGRIDIDs", "PharmGKBIDs", "UniProtIDs"];
+
+    fn from_columns(columns: &[&str]) -> Result<GeneTerm, String> {
+        Ok(GeneTerm {
+            symbol: columns[0].trim().to_string(),
+            name: columns[1].trim().to_string(),
+            id: tsv::prefixed_id(columns[2], "GENE:", "GeneID")?,
+            alt_gene_ids: tsv::list(columns[3]),
+            synonyms: tsv::list(columns[4]),
+            biogrid_ids: tsv::list(columns[5]),
+            pharmgkb_ids: tsv::list(columns[6]),
+            uniprot_ids: tsv::list(columns[7]),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::CtdError;
+    use crate::tsv::TsvReader;
+
+    fn read<T: TsvRecord>(input: &str) -> Vec<Result<T, CtdError>> {
+        TsvReader::<_, T>::new(input.as_bytes()).collect()
+    }
+
+    #[test]
+    fn chemical_rows() {
+        let input = "# Fields:\n# ChemicalName\tChemicalID\tCasRN\tDefinition\tParentIDs\tTreeNumbers\tParentTreeNumbers\tSynonyms\n#\n\
+            Water\tMESH:D014867\t7732-18-5\tH2O\tMESH:D007287|D000002\tD01.045|D01.650\tD01|D01.6\tDihydrogen oxide|Ice\n\
+            Root\tD000001\t\t\t\t\t\t\n";
+        let rows = read::<ChemicalTerm>(input).into_iter().collect::<Result<Vec<_>, _>>().unwrap();
+        assert_eq!(
+            rows[0],
+            ChemicalTerm {
+                name: String::from("Water"),
+                id: String::from("MESH:D014867"),
+                cas_rn: Some(String::from("7732-18-5")),
+                definition: Some(String::from("H2O")),
+                parent_ids: vec![String::from("MESH:D007287"), String::from("MESH:D000002")],
+                tree_numbers: vec![String::from("D01.045"), String::from("D01.650")],
+                parent_tree_numbers: vec![String::from("D01"), String::from("D01.6")],
+                synonyms: vec![String::from("Dihydrogen oxide"), String::from("Ice")],
+            }
+        );
+        assert_eq!((rows[1].id.as_str(), rows[1].cas_rn.as_ref(), rows[1].parent_ids.len()), ("MESH:D000001", None, 0));
+    }
+
+    #[test]
+    fn gene_rows() {
+        let input = "CYP1A1\tcytochrome P450 family 1 subfamily A member 1\t1543\t\tCP11|CYP1\t107935\tPA198\tP04798|A0N0X8\n";
+        let rows = read::<GeneTerm>(input).into_iter().collect::<Result<Vec<_>, _>>().unwrap();
+        assert_eq!(
+            rows,
+            vec![GeneTerm {
+                symbol: String::from("CYP1A1"),
+                name: String::from("cytochrome P450 family 1 subfamily A member 1"),
+                id: String::from("GENE:1543"),
+                alt_gene_ids: Vec::new(),
+                synonyms: vec![String::from("CP11"), String::from("CYP1")],
+                biogrid_ids: vec![String::from("107935")],
+                pharmgkb_ids: vec![String::from("PA198")],
+                uniprot_ids: vec![String::from("P04798"), String::from("A0N0X8")],
+            }]
+        );
+    }
+
+    #[test]
+    fn rows_without_an_id_are_rejected() {
+        let chemicals = read::<ChemicalTerm>("Unnamed\t\t\t\t\t\t\t\n");
+        assert!(matches!(&chemicals[..], [Err(e)] if e.is_recoverable() && e.to_string().contains("ChemicalID is empty")), "{:?}", chemicals);
+        let genes = read::<GeneTerm>("CYP1A1\tname\t \t\t\t\t\t\n");
+        assert!(matches!(&genes[..], [Err(e)] if e.to_string().contains("GeneID is empty")), "{:?}", genes);
+    }
+}
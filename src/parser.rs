@@ -0,0 +1,435 @@
+use crate::error::CtdError;
+use crate::jsonl::JsonLinesReader;
+use crate::model::*;
+use quick_xml::events::Event;
+use serde_path_to_error::Segment;
+use std::error;
+use std::fs;
+use std::io;
+use std::io::BufRead;
+use std::path;
+use std::str;
+
+/// Interactions read from any supported input format.
+pub type IXNIterator = Box<dyn Iterator<Item = Result<IXN, CtdError>>>;
+
+/// Reads interactions from structured XML or from JSON Lines written by `--dump-json`, telling them apart by the first non-blank byte.
+pub fn ctd_input_to_model(ctd_input_path: &path::Path) -> Result<IXNIterator, Box<dyn error::Error>> {
+    let mut input = open_input(ctd_input_path)?;
+    if input.fill_buf()?.iter().find(|b| !b.is_ascii_whitespace()) == Some(&b'{') {
+        debug!("reading json lines: {:?}", ctd_input_path);
+        return Ok(Box::new(JsonLinesReader::new(input)));
+    }
+    Ok(Box::new(IXNReader::new(input)))
+}
+
+/// Reads every interaction of an input into memory, failing on the first one that cannot be parsed.
+pub fn read_ixn_set(ctd_input_path: &path::Path) -> Result<IXNSet, Box<dyn error::Error>> {
+    Ok(IXNSet { ixns: ctd_input_to_model(ctd_input_path)?.collect::<Result<_, _>>()? })
+}
+
+/// Opens a possibly gzip-compressed input, detecting compression by the gzip magic bytes rather than the file extension.
+pub fn open_input(input_path: &path::Path) -> Result<Box<dyn io::BufRead>, Box<dyn error::Error>> {
+    let mut reader = io::BufReader::new(fs::File::open(input_path)?);
+    if reader.fill_buf()?.starts_with(&[0x1f, 0x8b]) {
+        debug!("decompressing: {:?}", input_path);
+        return Ok(Box::new(io::BufReader::new(flate2::bufread::MultiGzDecoder::new(reader))));
+    }
+    Ok(Box::new(reader))
+}
+
+/// Pull-based reader over `CTD_chem_gene_ixns_structured.xml`, yielding one `IXN` per `<ixn>` element.
+///
+/// Only the bytes of the current `<ixn>` are kept, so memory scales with the largest interaction rather than the whole file.
+/// Each `<ixn>` is scanned once to find its end and then deserialized from its original bytes: a quick-xml `Deserializer` cannot carry on after an error,
+/// so deserializing straight from the stream would let one bad interaction end the whole run.
+pub struct IXNReader<R: io::BufRead> {
+    reader: quick_xml::Reader<Recorder<R>>,
+    buf: Vec<u8>,
+    done: bool,
+}
+
+impl<R: io::BufRead> IXNReader<R> {
+    pub fn new(input: R) -> IXNReader<R> {
+        IXNReader { reader: quick_xml::Reader::from_reader(Recorder { inner: input, recorded: Vec::new() }), buf: Vec::new(), done: false }
+    }
+
+    /// The bytes of the next `<ixn>` element, as they are in the input.
+    fn next_ixn_xml(&mut self) -> Result<Option<Vec<u8>>, CtdError> {
+        loop {
+            // text before a tag ends with the tag's `<`, so the last byte is kept
+            let recorded = &mut self.reader.get_mut().recorded;
+            recorded.drain(..recorded.len().saturating_sub(1));
+            self.buf.clear();
+            let tag_len = match self.reader.read_event_into(&mut self.buf)? {
+                Event::Start(e) if e.name().as_ref() == b"ixn" => e.len() + 2,
+                Event::Empty(e) if e.name().as_ref() == b"ixn" => {
+                    let recorded = &self.reader.get_ref().recorded;
+                    return Ok(Some(recorded[recorded.len() - e.len() - 3..].to_vec()));
+                }
+                Event::Eof => return Ok(None),
+                _ => continue,
+            };
+            let start = self.reader.get_ref().recorded.len() - tag_len;
+            let mut depth = 1;
+            while depth > 0 {
+                self.buf.clear();
+                match self.reader.read_event_into(&mut self.buf)? {
+                    Event::Start(_) => depth += 1,
+                    Event::End(_) => depth -= 1,
+                    Event::Eof => return Err(CtdError::Xml { message: String::from("unexpected end of input inside <ixn>") }),
+                    _ => {}
+                }
+            }
+            return Ok(Some(self.reader.get_ref().recorded[start..].to_vec()));
+        }
+    }
+}
+
+/// Keeps a copy of the bytes the XML reader consumes; `IXNReader` drops them between interactions.
+struct Recorder<R: io::BufRead> {
+    inner: R,
+    recorded: Vec<u8>,
+}
+
+impl<R: io::BufRead> io::Read for Recorder<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.recorded.extend_from_slice(&buf[..n]);
+        Ok(n)
+    }
+}
+
+impl<R: io::BufRead> io::BufRead for Recorder<R> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        self.inner.fill_buf()
+    }
+
+    /// The consumed bytes are still in the inner buffer, so this does no io.
+    fn consume(&mut self, amt: usize) {
+        if let Ok(buf) = self.inner.fill_buf() {
+            self.recorded.extend_from_slice(&buf[..amt]);
+        }
+        self.inner.consume(amt);
+    }
+}
+
+/// Yields `Err` for an `<ixn>` that cannot be parsed and carries on with the next one; a `CtdError::Xml` ends the iteration.
+impl<R: io::BufRead> Iterator for IXNReader<R> {
+    type Item = Result<IXN, CtdError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        match self.next_ixn_xml() {
+            Ok(Some(ixn_xml)) => Some(parse_ixn(&ixn_xml)),
+            Ok(None) => {
+                self.done = true;
+                None
+            }
+            Err(e) => {
+                self.done = true;
+                Some(Err(e))
+            }
+        }
+    }
+}
+
+/// The `id` attribute of the `<ixn>` start tag, if it is a valid integer; used to attribute errors when the element as a whole does not deserialize.
+fn get_ixn_id(ixn_xml: &[u8]) -> Option<i32> {
+    let mut buf = Vec::new();
+    let id = match quick_xml::Reader::from_reader(ixn_xml).read_event_into(&mut buf).ok()? {
+        Event::Start(e) | Event::Empty(e) => e.try_get_attribute("id").ok()??.value.into_owned(),
+        _ => return None,
+    };
+    str::from_utf8(&id).ok()?.parse().ok()
+}
+
+/// Deserializes a single `<ixn>` element; of the axns directly under it, only those whose parent is the interaction itself are kept.
+pub fn parse_ixn(ixn_xml: &[u8]) -> Result<IXN, CtdError> {
+    let ixn_str = str::from_utf8(ixn_xml).map_err(|e| CtdError::Deserialize { ixn_id: get_ixn_id(ixn_xml), path: String::from("ixn"), message: e.to_string() })?;
+    let mut ixn: IXN = serde_path_to_error::deserialize(&mut quick_xml::de::Deserializer::from_str(ixn_str)).map_err(|e| deserialize_error(ixn_xml, e))?;
+    let id = ixn.id;
+    ixn.axns.retain(|axn| axn.parent_id == id);
+    debug!("{:?}", ixn);
+    Ok(ixn)
+}
+
+/// Names the element, and attribute if any, that an `<ixn>` failed to deserialize at.
+fn deserialize_error(ixn_xml: &[u8], error: serde_path_to_error::Error<quick_xml::DeError>) -> CtdError {
+    let ixn_id = get_ixn_id(ixn_xml);
+    let mut element = String::from("ixn");
+    // each element below <ixn> as its name and index among the children of that name
+    let mut steps: Vec<(&str, usize)> = Vec::new();
+    let mut attribute = None;
+    let mut name = "";
+    for segment in error.path().iter() {
+        match segment {
+            Segment::Map { key } => match key.strip_prefix('@') {
+                Some(key) => attribute = Some(key),
+                None => {
+                    element = format!("{}.{}", element, key);
+                    name = key;
+                }
+            },
+            Segment::Seq { index } => {
+                element = format!("{}[{}]", element, index);
+                steps.push((name, *index));
+            }
+            Segment::Enum { .. } | Segment::Unknown => {}
+        }
+    }
+    let missing_field = match error.inner() {
+        quick_xml::DeError::Custom(message) => message.strip_prefix("missing field `").and_then(|m| m.strip_suffix('`')),
+        _ => None,
+    };
+    match (error.inner(), attribute, missing_field) {
+        (quick_xml::DeError::InvalidInt(_), Some(attribute), _) => {
+            let value = attribute_value(ixn_xml, &steps, attribute).unwrap_or_default();
+            CtdError::BadInteger { ixn_id, element, attribute: attribute.to_string(), value }
+        }
+        (_, None, Some("text")) => CtdError::MissingText { ixn_id, element },
+        (_, None, Some(attribute)) => CtdError::MissingAttribute { ixn_id, element, attribute: attribute.to_string() },
+        (inner, attribute, _) => {
+            let path = attribute.map(|a| format!("{}.@{}", element, a)).unwrap_or(element);
+            CtdError::Deserialize { ixn_id, path, message: inner.to_string() }
+        }
+    }
+}
+
+/// The value of `attribute` on the element `steps` lead to from `<ixn>`, each step an element name and its index among the children of that name.
+fn attribute_value(ixn_xml: &[u8], steps: &[(&str, usize)], attribute: &str) -> Option<String> {
+    let mut reader = quick_xml::Reader::from_reader(ixn_xml);
+    let mut buf = Vec::new();
+    // steps matched by open elements so far, and children named like the next step seen in the last of them
+    let (mut matched, mut seen, mut depth) = (0, 0, 0);
+    loop {
+        buf.clear();
+        let (e, empty) = match reader.read_event_into(&mut buf).ok()? {
+            Event::Start(e) => (e, false),
+            Event::Empty(e) => (e, true),
+            Event::End(_) => {
+                depth -= 1;
+                continue;
+            }
+            Event::Eof => return None,
+            _ => continue,
+        };
+        depth += 1;
+        if depth == matched + 2 && matched < steps.len() && e.name().as_ref() == steps[matched].0.as_bytes() {
+            match seen == steps[matched].1 {
+                true => (matched, seen) = (matched + 1, 0),
+                false => seen += 1,
+            }
+        }
+        if depth == matched + 1 && matched == steps.len() {
+            return Some(e.try_get_attribute(attribute).ok()??.unescape_value().ok()?.into_owned());
+        }
+        if empty {
+            depth -= 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Read, Write};
+
+    const IXN_SET: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<ixnset>
+<ixn id="1">
+  <taxon id="9606">Homo sapiens</taxon>
+  <reference pmid="10"/>
+  <axn code="exp" degreecode="+" position="1" parentid="1">increases expression</axn>
+  <actor type="chemical" id="MESH:D000001" position="1" parentid="1">A &amp; B</actor>
+  <actor type="gene" id="GENE:1" position="2" parentid="1" form="mRNA" seqid="NM_1">G1</actor>
+</ixn>
+<ixn id="2">
+  <taxon id="9606">Homo sapiens</taxon>
+  <reference pmid="20"/>
+  <axn code="rxn" degreecode="+" position="1" parentid="2">affects</axn>
+  <axn code="exp" degreecode="-" position="1" parentid="3">decreases expression</axn>
+  <actor type="chemical" id="MESH:D000002" position="1" parentid="2">C</actor>
+  <actor type="ixn" id="3" position="2" parentid="2">
+    <axn code="exp" degreecode="-" position="1" parentid="3">decreases expression</axn>
+    <actor type="chemical" id="MESH:D000003" position="1" parentid="3">D</actor>
+    <actor type="gene" id="GENE:3" position="2" parentid="3">G3</actor>
+  </actor>
+</ixn>
+</ixnset>
+"#;
+
+    #[test]
+    fn parse_ixn_reads_attributes_and_text() {
+        let ixn = parse_ixn(br#"<ixn id="7"><taxon id="9606">Homo sapiens</taxon><reference pmid="1"/><reference pmid="2"/><axn code="b" degreecode="1" position="1" parentid="7">binds to</axn><actor type="gene" id="GENE:7" position="1" parentid="7" form="protein" formqualifier="mutant">G7</actor></ixn>"#).unwrap();
+        assert_eq!(ixn.id, 7);
+        assert_eq!(ixn.taxon, vec![Taxon::new(9606, String::from("Homo sapiens"))]);
+        assert_eq!(ixn.reference, vec![Reference::new(1), Reference::new(2)]);
+        assert_eq!(ixn.axns, vec![AXN::new(String::from("b"), '1', 1, 7, String::from("binds to"))]);
+        let actor = &ixn.actors[0];
+        assert_eq!((actor.form.as_deref(), actor.form_qualifier.as_deref(), actor.text.as_deref()), (Some("protein"), Some("mutant"), Some("G7")));
+    }
+
+    #[test]
+    fn parse_ixn_keeps_only_the_axns_of_the_interaction_itself() {
+        let ixn = IXNReader::new(IXN_SET.as_bytes()).nth(1).unwrap().unwrap();
+        assert_eq!(ixn.axns.iter().map(|axn| (axn.code.as_str(), axn.parent_id)).collect::<Vec<_>>(), vec![("rxn", 2)]);
+        let nested = &ixn.actors[1];
+        assert_eq!(nested.actor_type, "ixn");
+        assert_eq!(nested.axns.iter().map(|axn| (axn.code.as_str(), axn.parent_id)).collect::<Vec<_>>(), vec![("exp", 3)]);
+        assert_eq!(nested.actors.iter().map(|actor| actor.id.as_str()).collect::<Vec<_>>(), vec!["MESH:D000003", "GENE:3"]);
+    }
+
+    #[test]
+    fn parse_ixn_names_the_attribute_that_is_not_an_integer() {
+        let error = parse_ixn(br#"<ixn id="5"><axn code="exp" degreecode="+" position="x" parentid="5">text</axn></ixn>"#).unwrap_err();
+        let expected = CtdError::BadInteger { ixn_id: Some(5), element: String::from("ixn.axn[0]"), attribute: String::from("position"), value: String::from("x") };
+        assert_eq!(error, expected);
+        assert_eq!(error.to_string(), "ixn 5: ixn.axn[0] attribute 'position' is not an integer in range: \"x\"");
+        // the value of a nested element, found among siblings of other names
+        let nested = br#"<ixn id="6"><actor type="ixn" id="7" position="1" parentid="6"><axn code="exp" degreecode="+" position="1" parentid="7">t</axn><actor type="gene" id="G1" position="1" parentid="7">a</actor><actor type="gene" id="G2" position="300" parentid="7">b</actor></actor></ixn>"#;
+        let expected = CtdError::BadInteger { ixn_id: Some(6), element: String::from("ixn.actor[0].actor[1]"), attribute: String::from("position"), value: String::from("300") };
+        assert_eq!(parse_ixn(nested), Err(expected));
+        let bad_id = CtdError::BadInteger { ixn_id: None, element: String::from("ixn"), attribute: String::from("id"), value: String::from("a1") };
+        assert_eq!(parse_ixn(br#"<ixn id="a1"/>"#), Err(bad_id));
+    }
+
+    #[test]
+    fn parse_ixn_names_missing_attributes_and_text() {
+        let missing_attribute = br#"<ixn id="5"><actor type="ixn" id="6" position="1" parentid="5"><actor type="gene" id="G1" parentid="6">a</actor></actor></ixn>"#;
+        let expected = CtdError::MissingAttribute { ixn_id: Some(5), element: String::from("ixn.actor[0].actor[0]"), attribute: String::from("position") };
+        assert_eq!(parse_ixn(missing_attribute), Err(expected));
+        assert_eq!(
+            parse_ixn(br#"<ixn><taxon id="9606">Homo sapiens</taxon></ixn>"#),
+            Err(CtdError::MissingAttribute { ixn_id: None, element: String::from("ixn"), attribute: String::from("id") })
+        );
+        assert_eq!(
+            parse_ixn(br#"<ixn id="5"><taxon id="9606">Homo sapiens</taxon><taxon id="10090"/></ixn>"#),
+            Err(CtdError::MissingText { ixn_id: Some(5), element: String::from("ixn.taxon[1]") })
+        );
+    }
+
+    #[test]
+    fn parse_ixn_reports_the_path_of_any_other_mismatch() {
+        match parse_ixn(br#"<ixn id="5"><axn code="exp" degreecode="+-" position="1" parentid="5">text</axn></ixn>"#) {
+            Err(CtdError::Deserialize { ixn_id: Some(5), path, .. }) => assert_eq!(path, "ixn.axn[0].@degreecode"),
+            other => panic!("expected a deserialize error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn reader_keeps_the_original_bytes_across_buffer_refills() {
+        let small_buffer = IXNReader::new(io::BufReader::with_capacity(3, IXN_SET.as_bytes())).collect::<Result<Vec<_>, _>>().unwrap();
+        assert_eq!(small_buffer, IXNReader::new(IXN_SET.as_bytes()).collect::<Result<Vec<_>, _>>().unwrap());
+        let mut reader = IXNReader::new(io::BufReader::with_capacity(3, r#"<ixnset> <ixn id="1"><reference pmid="1"/></ixn><ixn id="2"/></ixnset>"#.as_bytes()));
+        assert_eq!(reader.next_ixn_xml().unwrap().unwrap(), br#"<ixn id="1"><reference pmid="1"/></ixn>"#);
+        assert_eq!(reader.next_ixn_xml().unwrap().unwrap(), br#"<ixn id="2"/>"#);
+        assert_eq!(reader.next_ixn_xml().unwrap(), None);
+    }
+
+    #[test]
+    fn reader_streams_each_ixn_element() {
+        let ixns = IXNReader::new(IXN_SET.as_bytes()).collect::<Result<Vec<_>, _>>().unwrap();
+        assert_eq!(ixns.iter().map(|ixn| ixn.id).collect::<Vec<_>>(), vec![1, 2]);
+        assert_eq!(ixns[0].actors[0].text.as_deref(), Some("A & B"));
+    }
+
+    #[test]
+    fn reader_reads_a_self_closing_ixn() {
+        let ixns = IXNReader::new(r#"<ixnset><ixn id="1"/></ixnset>"#.as_bytes()).collect::<Result<Vec<_>, _>>().unwrap();
+        assert_eq!(ixns.iter().map(|ixn| ixn.id).collect::<Vec<_>>(), vec![1]);
+    }
+
+    #[test]
+    fn reader_carries_on_after_an_interaction_that_does_not_parse() {
+        let input = r#"<ixnset><ixn id="1"><axn code="exp" degreecode="+" position="300" parentid="1">text</axn></ixn><ixn id="2"/></ixnset>"#;
+        let results = IXNReader::new(input.as_bytes()).collect::<Vec<_>>();
+        assert_eq!(results.len(), 2);
+        assert!(matches!(results[0], Err(CtdError::BadInteger { ixn_id: Some(1), .. })));
+        assert_eq!(results[1].as_ref().unwrap().id, 2);
+    }
+
+    #[test]
+    fn reader_stops_at_a_truncated_ixn() {
+        let input = r#"<ixnset><ixn id="1"><taxon id="9606">Homo sapiens</taxon>"#;
+        let results = IXNReader::new(input.as_bytes()).collect::<Vec<_>>();
+        assert_eq!(results.len(), 1);
+        assert!(matches!(results[0], Err(CtdError::Xml { .. })));
+    }
+
+    /// Writes `contents` to a file of its own under the system temp directory.
+    fn temp_file(name: &str, contents: &[u8]) -> path::PathBuf {
+        let path = std::env::temp_dir().join(format!("ctd-to-owl-{}-{}", std::process::id(), name));
+        fs::write(&path, contents).unwrap();
+        path
+    }
+
+    fn gzip(contents: &[u8]) -> Vec<u8> {
+        let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all(contents).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    fn read_to_string(path: &path::Path) -> String {
+        let mut contents = String::new();
+        open_input(path).unwrap().read_to_string(&mut contents).unwrap();
+        contents
+    }
+
+    #[test]
+    fn open_input_detects_gzip_by_its_magic_bytes() {
+        let plain = temp_file("plain.tsv.gz", b"MESH:D000001\tCHEBI:1\n");
+        let compressed = temp_file("compressed.tsv", &gzip(b"MESH:D000001\tCHEBI:1\n"));
+        assert_eq!(read_to_string(&plain), "MESH:D000001\tCHEBI:1\n");
+        assert_eq!(read_to_string(&compressed), "MESH:D000001\tCHEBI:1\n");
+        fs::remove_file(plain).unwrap();
+        fs::remove_file(compressed).unwrap();
+    }
+
+    #[test]
+    fn open_input_reads_every_member_of_a_multi_member_gzip() {
+        let path = temp_file("members.gz", &[gzip(b"first\n"), gzip(b"second\n")].concat());
+        assert_eq!(read_to_string(&path), "first\nsecond\n");
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn gzipped_xml_is_streamed() {
+        let path = temp_file("ixns.xml.gz", &gzip(IXN_SET.as_bytes()));
+        assert_eq!(read_ixn_set(&path).unwrap().ixns.iter().map(|ixn| ixn.id).collect::<Vec<_>>(), vec![1, 2]);
+        fs::remove_file(path).unwrap();
+    }
+
+    fn dump(ixns: &[IXN]) -> Vec<u8> {
+        let mut out = Vec::new();
+        for ixn in ixns {
+            crate::jsonl::write_ixn(&mut out, ixn).unwrap();
+        }
+        out
+    }
+
+    #[test]
+    fn json_lines_are_told_apart_from_xml_by_content() {
+        let from_xml = IXNReader::new(IXN_SET.as_bytes()).collect::<Result<Vec<_>, _>>().unwrap();
+        let xml = temp_file("detect.jsonl", IXN_SET.as_bytes());
+        let json = temp_file("detect.xml", &[b"\n  \n".to_vec(), dump(&from_xml)].concat());
+        let gzipped_json = temp_file("detect.json.gz", &gzip(&dump(&from_xml)));
+        for path in [&xml, &json, &gzipped_json] {
+            assert_eq!(read_ixn_set(path).unwrap().ixns, from_xml, "{:?}", path);
+            fs::remove_file(path).unwrap();
+        }
+    }
+
+    #[test]
+    fn a_json_line_that_is_not_an_interaction_is_reported_by_line() {
+        let path = temp_file("bad.jsonl", b"{\"id\":1,\"taxon\":[],\"reference\":[],\"axns\":[],\"actors\":[]}\n\n{\"id\":\"x\",\"taxon\":[],\"reference\":[],\"axns\":[],\"actors\":[]}\n{\"id\":3,\"taxon\":[],\"reference\":[],\"axns\":[],\"actors\":[]}\n");
+        let results = ctd_input_to_model(&path).unwrap().collect::<Vec<_>>();
+        assert_eq!(results.len(), 3);
+        assert_eq!(results[0].as_ref().unwrap().id, 1);
+        assert!(matches!(results[1], Err(CtdError::Json { line: 3, .. })));
+        assert_eq!(results[2].as_ref().unwrap().id, 3);
+        fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,548 @@
+use horned_owl::model::*;
+use horned_owl::ontology::axiom_mapped::AxiomMappedOntology;
+use itertools::Itertools;
+use std::collections;
+use std::error;
+use std::fmt;
+use std::io;
+
+pub const RDF: &str = "http://www.w3.org/1999/02/22-rdf-syntax-ns#";
+pub const RDFS: &str = "http://www.w3.org/2000/01/rdf-schema#";
+pub const OWL: &str = "http://www.w3.org/2002/07/owl#";
+pub const XSD: &str = "http://www.w3.org/2001/XMLSchema#";
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Term {
+    IRI(String),
+    BlankNode(String),
+    Literal { value: String, datatype: Option<String>, lang: Option<String> },
+}
+
+impl Term {
+    fn vocab(namespace: &str, local: &str) -> Term {
+        Term::IRI(format!("{}{}", namespace, local))
+    }
+}
+
+impl From<&IRI> for Term {
+    fn from(iri: &IRI) -> Term {
+        Term::IRI(iri.to_string())
+    }
+}
+
+impl From<&Literal> for Term {
+    fn from(literal: &Literal) -> Term {
+        match literal {
+            Literal::Simple { literal } => Term::Literal { value: literal.clone(), datatype: None, lang: None },
+            Literal::Language { literal, lang } => Term::Literal { value: literal.clone(), datatype: None, lang: Some(lang.clone()) },
+            Literal::Datatype { literal, datatype_iri } => Term::Literal { value: literal.clone(), datatype: Some(datatype_iri.to_string()), lang: None },
+        }
+    }
+}
+
+impl From<&AnnotationValue> for Term {
+    fn from(value: &AnnotationValue) -> Term {
+        match value {
+            AnnotationValue::IRI(iri) => iri.into(),
+            AnnotationValue::Literal(literal) => literal.into(),
+        }
+    }
+}
+
+/// N-Triples rendering of a term, also valid in Turtle.
+impl fmt::Display for Term {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Term::IRI(iri) => write!(f, "<{}>", escape_iri(iri)),
+            Term::BlankNode(id) => write!(f, "_:{}", id),
+            Term::Literal { value, datatype, lang } => {
+                write!(f, "\"{}\"", escape_literal(value))?;
+                if let Some(lang) = lang {
+                    write!(f, "@{}", lang)?;
+                } else if let Some(datatype) = datatype {
+                    write!(f, "^^<{}>", escape_iri(datatype))?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Triple {
+    pub subject: Term,
+    pub predicate: Term,
+    pub object: Term,
+}
+
+impl Triple {
+    fn new(subject: Term, predicate: Term, object: Term) -> Triple {
+        Triple { subject, predicate, object }
+    }
+}
+
+/// Maps axioms onto triples following the OWL 2 mapping to RDF graphs, for the subset of OWL the converter emits.
+///
+/// Blank node labels are unique per `TripleBuilder`, so every document should be written with a single builder.
+pub struct TripleBuilder {
+    ontology: Term,
+    blank_node_prefix: String,
+    next_blank_node: usize,
+}
+
+impl TripleBuilder {
+    pub fn new(ontology: &AxiomMappedOntology) -> TripleBuilder {
+        let ontology = match &ontology.id().iri {
+            Some(iri) => iri.into(),
+            None => Term::BlankNode(String::from("ontology")),
+        };
+        TripleBuilder { ontology, blank_node_prefix: String::new(), next_blank_node: 0 }
+    }
+
+    /// A builder for one named graph that is also the ontology of its axioms; blank node labels start with `blank_node_prefix` so they stay unique across graphs.
+    pub fn for_graph(graph: &IRI, blank_node_prefix: &str) -> TripleBuilder {
+        TripleBuilder { ontology: graph.into(), blank_node_prefix: blank_node_prefix.to_string(), next_blank_node: 0 }
+    }
+
+    /// A builder for one of several chunks written as a single document; blank node labels start with `blank_node_prefix` so they stay unique across chunks.
+    pub fn for_chunk(ontology: &AxiomMappedOntology, blank_node_prefix: &str) -> TripleBuilder {
+        TripleBuilder { blank_node_prefix: blank_node_prefix.to_string(), ..TripleBuilder::new(ontology) }
+    }
+
+    pub fn ontology(&self) -> &Term {
+        &self.ontology
+    }
+
+    fn blank_node(&mut self) -> Term {
+        self.next_blank_node += 1;
+        Term::BlankNode(format!("{}b{}", self.blank_node_prefix, self.next_blank_node))
+    }
+
+    pub fn header_triples(&self) -> Vec<Triple> {
+        vec![Triple::new(self.ontology.clone(), Term::vocab(RDF, "type"), Term::vocab(OWL, "Ontology"))]
+    }
+
+    pub fn axiom_triples(&mut self, annotated_axiom: &AnnotatedAxiom) -> Result<Vec<Triple>, Box<dyn error::Error>> {
+        let mut triples = Vec::new();
+        let main_triple = match &annotated_axiom.axiom {
+            Axiom::OntologyAnnotation(OntologyAnnotation(ann)) => Triple::new(self.ontology.clone(), (&ann.ap.0).into(), (&ann.av).into()),
+            Axiom::DeclareClass(DeclareClass(c)) => declaration(&c.0, "Class"),
+            Axiom::DeclareObjectProperty(DeclareObjectProperty(op)) => declaration(&op.0, "ObjectProperty"),
+            Axiom::DeclareAnnotationProperty(DeclareAnnotationProperty(ap)) => declaration(&ap.0, "AnnotationProperty"),
+            Axiom::DeclareDataProperty(DeclareDataProperty(dp)) => declaration(&dp.0, "DatatypeProperty"),
+            Axiom::DeclareNamedIndividual(DeclareNamedIndividual(i)) => declaration(&i.0, "NamedIndividual"),
+            Axiom::ClassAssertion(ClassAssertion { ce, i }) => {
+                let object = self.class_expression(ce, &mut triples)?;
+                Triple::new((&i.0).into(), Term::vocab(RDF, "type"), object)
+            }
+            Axiom::ObjectPropertyAssertion(ObjectPropertyAssertion { ope, from, to }) => match ope {
+                ObjectPropertyExpression::ObjectProperty(op) => Triple::new((&from.0).into(), (&op.0).into(), (&to.0).into()),
+                ObjectPropertyExpression::InverseObjectProperty(op) => Triple::new((&to.0).into(), (&op.0).into(), (&from.0).into()),
+            },
+            Axiom::AnnotationAssertion(AnnotationAssertion { subject, ann }) => Triple::new(subject.into(), (&ann.ap.0).into(), (&ann.av).into()),
+            Axiom::SubClassOf(SubClassOf { sup, sub }) => {
+                let subject = self.class_expression(sub, &mut triples)?;
+                let object = self.class_expression(sup, &mut triples)?;
+                Triple::new(subject, Term::vocab(RDFS, "subClassOf"), object)
+            }
+            Axiom::EquivalentClasses(EquivalentClasses(ces)) if ces.len() == 2 => {
+                let subject = self.class_expression(&ces[0], &mut triples)?;
+                let object = self.class_expression(&ces[1], &mut triples)?;
+                Triple::new(subject, Term::vocab(OWL, "equivalentClass"), object)
+            }
+            Axiom::NegativeObjectPropertyAssertion(NegativeObjectPropertyAssertion { ope: ObjectPropertyExpression::ObjectProperty(op), from, to }) => {
+                // a negative assertion is its own blank node, which carries any axiom annotations directly
+                let assertion = self.blank_node();
+                triples.push(Triple::new(assertion.clone(), Term::vocab(RDF, "type"), Term::vocab(OWL, "NegativePropertyAssertion")));
+                triples.push(Triple::new(assertion.clone(), Term::vocab(OWL, "sourceIndividual"), (&from.0).into()));
+                triples.push(Triple::new(assertion.clone(), Term::vocab(OWL, "assertionProperty"), (&op.0).into()));
+                triples.push(Triple::new(assertion.clone(), Term::vocab(OWL, "targetIndividual"), (&to.0).into()));
+                for ann in annotated_axiom.ann.iter() {
+                    triples.push(Triple::new(assertion.clone(), (&ann.ap.0).into(), (&ann.av).into()));
+                }
+                return Ok(triples);
+            }
+            axiom => return Err(format!("axiom not supported by the rdf writer: {:?}", axiom).into()),
+        };
+
+        if !annotated_axiom.ann.is_empty() {
+            let reification = self.blank_node();
+            triples.push(Triple::new(reification.clone(), Term::vocab(RDF, "type"), Term::vocab(OWL, "Axiom")));
+            triples.push(Triple::new(reification.clone(), Term::vocab(OWL, "annotatedSource"), main_triple.subject.clone()));
+            triples.push(Triple::new(reification.clone(), Term::vocab(OWL, "annotatedProperty"), main_triple.predicate.clone()));
+            triples.push(Triple::new(reification.clone(), Term::vocab(OWL, "annotatedTarget"), main_triple.object.clone()));
+            for ann in annotated_axiom.ann.iter() {
+                triples.push(Triple::new(reification.clone(), (&ann.ap.0).into(), (&ann.av).into()));
+            }
+        }
+        triples.insert(0, main_triple);
+        Ok(triples)
+    }
+
+    fn class_expression(&mut self, ce: &ClassExpression, triples: &mut Vec<Triple>) -> Result<Term, Box<dyn error::Error>> {
+        match ce {
+            ClassExpression::Class(c) => Ok((&c.0).into()),
+            ClassExpression::ObjectSomeValuesFrom { ope: ObjectPropertyExpression::ObjectProperty(op), bce } => {
+                let restriction = self.blank_node();
+                let filler = self.class_expression(bce, triples)?;
+                triples.push(Triple::new(restriction.clone(), Term::vocab(RDF, "type"), Term::vocab(OWL, "Restriction")));
+                triples.push(Triple::new(restriction.clone(), Term::vocab(OWL, "onProperty"), (&op.0).into()));
+                triples.push(Triple::new(restriction.clone(), Term::vocab(OWL, "someValuesFrom"), filler));
+                Ok(restriction)
+            }
+            ClassExpression::ObjectIntersectionOf(operands) => {
+                let class = self.blank_node();
+                let list = self.list(operands, triples)?;
+                triples.push(Triple::new(class.clone(), Term::vocab(RDF, "type"), Term::vocab(OWL, "Class")));
+                triples.push(Triple::new(class.clone(), Term::vocab(OWL, "intersectionOf"), list));
+                Ok(class)
+            }
+            ce => Err(format!("class expression not supported by the rdf writer: {:?}", ce).into()),
+        }
+    }
+
+    fn list(&mut self, operands: &[ClassExpression], triples: &mut Vec<Triple>) -> Result<Term, Box<dyn error::Error>> {
+        let mut rest = Term::vocab(RDF, "nil");
+        for operand in operands.iter().rev() {
+            let first = self.class_expression(operand, triples)?;
+            let node = self.blank_node();
+            triples.push(Triple::new(node.clone(), Term::vocab(RDF, "first"), first));
+            triples.push(Triple::new(node.clone(), Term::vocab(RDF, "rest"), rest));
+            rest = node;
+        }
+        Ok(rest)
+    }
+
+    /// All triples of the ontology, header first.
+    pub fn ontology_triples(&mut self, ontology: &AxiomMappedOntology) -> Result<Vec<Triple>, Box<dyn error::Error>> {
+        self.header_and_axiom_triples(ontology.i().iter())
+    }
+
+    fn header_and_axiom_triples<'a, I: IntoIterator<Item = &'a AnnotatedAxiom>>(&mut self, axioms: I) -> Result<Vec<Triple>, Box<dyn error::Error>> {
+        let mut triples = self.header_triples();
+        for annotated_axiom in axioms {
+            triples.append(&mut self.axiom_triples(annotated_axiom)?);
+        }
+        Ok(triples)
+    }
+}
+
+fn declaration(iri: &IRI, owl_type: &str) -> Triple {
+    Triple::new(iri.into(), Term::vocab(RDF, "type"), Term::vocab(OWL, owl_type))
+}
+
+fn escape_literal(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            '"' => escaped.push_str("\\\""),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+fn escape_iri(iri: &str) -> String {
+    let mut escaped = String::with_capacity(iri.len());
+    for c in iri.chars() {
+        match c {
+            '\u{0}'..='\u{20}' | '<' | '>' | '"' | '{' | '}' | '|' | '^' | '`' | '\\' => escaped.push_str(&format!("\\u{:04X}", c as u32)),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+pub fn write_ntriples<W: io::Write>(write: &mut W, ontology: &AxiomMappedOntology) -> Result<(), Box<dyn error::Error>> {
+    let mut triple_builder = TripleBuilder::new(ontology);
+    for triple in triple_builder.ontology_triples(ontology)? {
+        writeln!(write, "{} {} {} .", triple.subject, triple.predicate, triple.object)?;
+    }
+    Ok(())
+}
+
+/// Turtle output with one block per subject; IRIs are abbreviated with `prefix_mapping` wherever the local part is a plain name.
+pub fn write_turtle<W: io::Write>(write: &mut W, ontology: &AxiomMappedOntology, prefix_mapping: &curie::PrefixMapping) -> Result<(), Box<dyn error::Error>> {
+    let mut triple_builder = TripleBuilder::new(ontology);
+    let triples = triple_builder.ontology_triples(ontology)?;
+    let prefixes = TurtlePrefixes::new(prefix_mapping);
+    prefixes.write_header(write)?;
+
+    write_turtle_blocks(write, &prefixes, &triple_builder.ontology, &triples, "")
+}
+
+/// Writes `triples` grouped into one block per subject, with `lead` first as in other Turtle serializers.
+pub(crate) fn write_turtle_blocks<W: io::Write>(write: &mut W, prefixes: &TurtlePrefixes, lead: &Term, triples: &[Triple], indent: &str) -> Result<(), Box<dyn error::Error>> {
+    let mut by_subject: collections::BTreeMap<&Term, Vec<&Triple>> = collections::BTreeMap::new();
+    for triple in triples.iter() {
+        by_subject.entry(&triple.subject).or_default().push(triple);
+    }
+    let ordered = by_subject.remove(lead).into_iter().map(|t| (lead, t)).chain(by_subject);
+    let separator = format!(" ;\n{}    ", indent);
+    for (subject, subject_triples) in ordered {
+        writeln!(write)?;
+        let predicate_objects = subject_triples.iter().map(|t| format!("{} {}", prefixes.predicate(&t.predicate), prefixes.term(&t.object))).join(&separator);
+        writeln!(write, "{}{} {} .", indent, prefixes.term(subject), predicate_objects)?;
+    }
+    Ok(())
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DatasetFormat {
+    NQuads,
+    TriG,
+}
+
+/// Writes an RDF dataset with one named graph per interaction, as the Scala ctd-to-owl did with one model per `CTDIXN` IRI.
+///
+/// The header goes in the default graph; each named graph also types its graph IRI as an `owl:Ontology`.
+pub struct DatasetWriter {
+    format: DatasetFormat,
+    prefixes: TurtlePrefixes,
+}
+
+impl DatasetWriter {
+    pub fn new(format: DatasetFormat, prefix_mapping: &curie::PrefixMapping) -> DatasetWriter {
+        DatasetWriter { format, prefixes: TurtlePrefixes::new(prefix_mapping) }
+    }
+
+    /// Prefixes (TriG only) and the default graph, written once per document before any named graph.
+    pub fn write_header<W: io::Write>(&self, write: &mut W, axioms: &[Axiom]) -> Result<(), Box<dyn error::Error>> {
+        let mut triple_builder = TripleBuilder { ontology: Term::BlankNode(String::from("ontology")), blank_node_prefix: String::new(), next_blank_node: 0 };
+        let annotated_axioms = axioms.iter().unique().cloned().map(AnnotatedAxiom::from).collect_vec();
+        let triples = triple_builder.header_and_axiom_triples(annotated_axioms.iter())?;
+        match self.format {
+            DatasetFormat::NQuads => write_quads(write, &triples, None),
+            DatasetFormat::TriG => {
+                self.prefixes.write_header(write)?;
+                write_turtle_blocks(write, &self.prefixes, &triple_builder.ontology, &triples, "")
+            }
+        }
+    }
+
+    /// One named graph; `blank_node_prefix` must differ between graphs of the same document.
+    pub fn write_graph<W: io::Write>(&self, write: &mut W, graph: &IRI, blank_node_prefix: &str, axioms: &[Axiom]) -> Result<(), Box<dyn error::Error>> {
+        let mut triple_builder = TripleBuilder::for_graph(graph, blank_node_prefix);
+        let annotated_axioms = axioms.iter().unique().cloned().map(AnnotatedAxiom::from).collect_vec();
+        let triples = triple_builder.header_and_axiom_triples(annotated_axioms.iter())?;
+        match self.format {
+            DatasetFormat::NQuads => write_quads(write, &triples, Some(&triple_builder.ontology)),
+            DatasetFormat::TriG => {
+                writeln!(write)?;
+                writeln!(write, "{} {{", self.prefixes.term(&triple_builder.ontology))?;
+                write_turtle_blocks(write, &self.prefixes, &triple_builder.ontology, &triples, "    ")?;
+                writeln!(write, "}}")?;
+                Ok(())
+            }
+        }
+    }
+}
+
+fn write_quads<W: io::Write>(write: &mut W, triples: &[Triple], graph: Option<&Term>) -> Result<(), Box<dyn error::Error>> {
+    for triple in triples.iter() {
+        match graph {
+            Some(graph) => writeln!(write, "{} {} {} {} .", triple.subject, triple.predicate, triple.object, graph)?,
+            None => writeln!(write, "{} {} {} .", triple.subject, triple.predicate, triple.object)?,
+        }
+    }
+    Ok(())
+}
+
+pub(crate) struct TurtlePrefixes {
+    prefixes: Vec<(String, String)>,
+}
+
+impl TurtlePrefixes {
+    pub(crate) fn new(prefix_mapping: &curie::PrefixMapping) -> TurtlePrefixes {
+        // longest namespace first, so that e.g. CHEBI: wins over a broader OBO prefix
+        let prefixes = prefix_mapping.mappings().map(|(p, ns)| (p.clone(), ns.clone())).sorted_by(|a, b| b.1.len().cmp(&a.1.len()).then(a.0.cmp(&b.0))).collect_vec();
+        TurtlePrefixes { prefixes }
+    }
+
+    pub(crate) fn write_header<W: io::Write>(&self, write: &mut W) -> io::Result<()> {
+        for (prefix, namespace) in self.prefixes.iter().sorted() {
+            writeln!(write, "@prefix {}: <{}> .", prefix, escape_iri(namespace))?;
+        }
+        Ok(())
+    }
+
+    pub(crate) fn predicate(&self, predicate: &Term) -> String {
+        match predicate {
+            Term::IRI(iri) if iri == &format!("{}type", RDF) => String::from("a"),
+            _ => self.term(predicate),
+        }
+    }
+
+    pub(crate) fn term(&self, term: &Term) -> String {
+        if let Term::IRI(iri) = term {
+            for (prefix, namespace) in self.prefixes.iter() {
+                if let Some(local) = iri.strip_prefix(namespace.as_str()) {
+                    if is_plain_local_name(local) {
+                        return format!("{}:{}", prefix, local);
+                    }
+                }
+            }
+        }
+        term.to_string()
+    }
+}
+
+/// A conservative subset of Turtle's PN_LOCAL that needs no escaping.
+fn is_plain_local_name(local: &str) -> bool {
+    !local.is_empty()
+        && local.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-' || c == '.')
+        && local.chars().next().is_some_and(|c| c.is_ascii_alphanumeric() || c == '_')
+        && !local.ends_with('.')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rio_api::parser::{QuadsParser, TriplesParser};
+
+    fn literal(value: &str) -> Term {
+        Term::Literal { value: value.to_string(), datatype: None, lang: None }
+    }
+
+    fn prefixes() -> TurtlePrefixes {
+        TurtlePrefixes::new(&crate::get_prefix_mapping())
+    }
+
+    /// A taxon-specific interaction individual with a class assertion, a restriction and an annotated assertion carrying the characters that need escaping.
+    fn ontology() -> AxiomMappedOntology {
+        let build = Build::new();
+        let mut ontology = AxiomMappedOntology::default();
+        let ixn = build.named_individual(format!("{}2#0", crate::CTDIXN));
+        let label = build.annotation_property(format!("{}label", RDFS));
+        let chebi = build.class(format!("{}1", crate::CHEBI));
+        ontology.insert(Axiom::DeclareNamedIndividual(DeclareNamedIndividual(ixn.clone())));
+        ontology.insert(Axiom::DeclareClass(DeclareClass(chebi.clone())));
+        ontology.insert(Axiom::ClassAssertion(ClassAssertion {
+            ce: ClassExpression::ObjectIntersectionOf(vec![
+                chebi.clone().into(),
+                ClassExpression::ObjectSomeValuesFrom { ope: build.object_property(format!("{}RO_0002233", crate::OBO)).into(), bce: Box::new(chebi.into()) },
+            ]),
+            i: ixn.clone(),
+        }));
+        ontology.insert(AnnotatedAxiom::new(
+            Axiom::AnnotationAssertion(AnnotationAssertion::new(
+                ixn.0,
+                Annotation { ap: label.clone(), av: AnnotationValue::Literal(Literal::Simple { literal: String::from("say \"foo & bar\"\nback\\slash") }) },
+            )),
+            vec![Annotation { ap: build.annotation_property(format!("{}source", crate::DC)), av: AnnotationValue::IRI(build.iri(format!("{}/15735062", crate::PMID))) }]
+                .into_iter()
+                .collect(),
+        ));
+        ontology
+    }
+
+    fn rio_term(term: rio_api::model::Term) -> Term {
+        match term {
+            rio_api::model::Term::NamedNode(n) => Term::IRI(n.iri.to_string()),
+            rio_api::model::Term::BlankNode(b) => Term::BlankNode(b.id.to_string()),
+            rio_api::model::Term::Literal(rio_api::model::Literal::Simple { value }) => literal(value),
+            rio_api::model::Term::Literal(rio_api::model::Literal::LanguageTaggedString { value, language }) => {
+                Term::Literal { value: value.to_string(), datatype: None, lang: Some(language.to_string()) }
+            }
+            rio_api::model::Term::Literal(rio_api::model::Literal::Typed { value, datatype }) => {
+                Term::Literal { value: value.to_string(), datatype: Some(datatype.iri.to_string()), lang: None }
+            }
+        }
+    }
+
+    fn rio_triple(t: rio_api::model::Triple) -> Triple {
+        Triple::new(rio_term(t.subject.into()), Term::IRI(t.predicate.iri.to_string()), rio_term(t.object))
+    }
+
+    fn parse_triples<P: TriplesParser>(mut parser: P) -> collections::BTreeSet<Triple>
+    where
+        P::Error: From<rio_turtle::TurtleError>,
+    {
+        let mut triples = collections::BTreeSet::new();
+        parser
+            .parse_all(&mut |t| {
+                triples.insert(rio_triple(t));
+                Ok(()) as Result<(), P::Error>
+            })
+            .unwrap();
+        triples
+    }
+
+    #[test]
+    fn literals_escape_quotes_backslashes_and_line_breaks() {
+        assert_eq!(literal("say \"foo & bar\"\n\tback\\slash\r").to_string(), r#""say \"foo & bar\"\n\tback\\slash\r""#);
+        assert_eq!(Term::Literal { value: String::from("0.5"), datatype: Some(format!("{}double", XSD)), lang: None }.to_string(), format!("\"0.5\"^^<{}double>", XSD));
+        assert_eq!(Term::Literal { value: String::from("Ratte"), datatype: None, lang: Some(String::from("de")) }.to_string(), "\"Ratte\"@de");
+    }
+
+    #[test]
+    fn iris_escape_characters_not_allowed_in_iriref() {
+        assert_eq!(Term::IRI(format!("{}1&x=<a b>", crate::CTDIXN)).to_string(), format!("<{}1&x=\\u003Ca\\u0020b\\u003E>", crate::CTDIXN));
+        assert_eq!(Term::IRI(String::from("http://example.org/{\"|^`\\}")).to_string(), "<http://example.org/\\u007B\\u0022\\u007C\\u005E\\u0060\\u005C\\u007D>");
+    }
+
+    #[test]
+    fn turtle_abbreviates_only_plain_local_names() {
+        let prefixes = prefixes();
+        assert_eq!(prefixes.term(&Term::IRI(format!("{}1", crate::CHEBI))), "CHEBI:1");
+        assert_eq!(prefixes.term(&Term::IRI(format!("{}1545", crate::NCBIGENE))), "NCBIGENE:1545");
+        assert_eq!(prefixes.term(&Term::IRI(format!("{}RO_0002233", crate::OBO))), format!("<{}RO_0002233>", crate::OBO));
+        // PMID: is declared without a trailing slash, so the local part of a PubMed IRI starts with one and is written in full
+        assert_eq!(prefixes.term(&Term::IRI(format!("{}/15735062", crate::PMID))), format!("<{}/15735062>", crate::PMID));
+        assert_eq!(prefixes.term(&Term::IRI(format!("{}D000001.", crate::MESH))), format!("<{}D000001.>", crate::MESH));
+        assert_eq!(prefixes.term(&Term::IRI(format!("{}2", crate::CTDIXN))), format!("<{}2>", crate::CTDIXN));
+        assert_eq!(prefixes.predicate(&Term::vocab(RDF, "type")), "a");
+        assert_eq!(prefixes.term(&literal("CHEBI:1")), "\"CHEBI:1\"");
+    }
+
+    #[test]
+    fn turtle_prefers_the_longest_namespace() {
+        let mut prefix_mapping = crate::get_prefix_mapping();
+        prefix_mapping.add_prefix("obo", crate::OBO).unwrap();
+        let prefixes = TurtlePrefixes::new(&prefix_mapping);
+        assert_eq!(prefixes.term(&Term::IRI(format!("{}1", crate::CHEBI))), "CHEBI:1");
+        assert_eq!(prefixes.term(&Term::IRI(format!("{}RO_0002233", crate::OBO))), "obo:RO_0002233");
+    }
+
+    #[test]
+    fn blank_node_labels_are_unique_per_graph() {
+        let build = Build::new();
+        let axioms = ontology().i().iter().map(|a| a.axiom.clone()).collect_vec();
+        let writer = DatasetWriter::new(DatasetFormat::NQuads, &crate::get_prefix_mapping());
+        let mut out = Vec::new();
+        writer.write_graph(&mut out, &build.iri(format!("{}2", crate::CTDIXN)), "ixn2", &axioms).unwrap();
+        writer.write_graph(&mut out, &build.iri(format!("{}3", crate::CTDIXN)), "ixn3", &axioms).unwrap();
+        let mut graphs_by_blank_node: collections::BTreeMap<String, collections::BTreeSet<String>> = collections::BTreeMap::new();
+        rio_turtle::NQuadsParser::new(out.as_slice())
+            .unwrap()
+            .parse_all(&mut |q| {
+                if let rio_api::model::NamedOrBlankNode::BlankNode(b) = q.subject {
+                    graphs_by_blank_node.entry(b.id.to_string()).or_default().insert(q.graph_name.unwrap().to_string());
+                }
+                Ok(()) as Result<(), rio_turtle::TurtleError>
+            })
+            .unwrap();
+        assert!(graphs_by_blank_node.contains_key("ixn2b1"));
+        assert!(graphs_by_blank_node.contains_key("ixn3b1"));
+        assert!(graphs_by_blank_node.values().all(|graphs| graphs.len() == 1), "{:?}", graphs_by_blank_node);
+    }
+
+    #[test]
+    fn ntriples_and_turtle_round_trip() {
+        let ontology = ontology();
+        let expected = TripleBuilder::new(&ontology).ontology_triples(&ontology).unwrap().into_iter().collect::<collections::BTreeSet<_>>();
+        assert!(expected.contains(&Triple::new(Term::IRI(format!("{}2#0", crate::CTDIXN)), Term::vocab(RDFS, "label"), literal("say \"foo & bar\"\nback\\slash"))));
+
+        let mut ntriples = Vec::new();
+        write_ntriples(&mut ntriples, &ontology).unwrap();
+        assert_eq!(parse_triples(rio_turtle::NTriplesParser::new(ntriples.as_slice()).unwrap()), expected);
+
+        let mut turtle = Vec::new();
+        write_turtle(&mut turtle, &ontology, &crate::get_prefix_mapping()).unwrap();
+        assert_eq!(parse_triples(rio_turtle::TurtleParser::new(turtle.as_slice(), "").unwrap()), expected);
+    }
+}
@@ -0,0 +1,157 @@
+use crate::error::CtdError;
+use std::io;
+use std::marker;
+use std::str;
+
+/// A row of one of CTD's tab-separated tables.
+pub trait TsvRecord: Sized {
+    /// The table's columns, in order, as listed in its `# Fields:` comment.
+    const COLUMNS: &'static [&'static str];
+
+    /// Parses a row that has exactly `COLUMNS.len()` columns.
+    fn from_columns(columns: &[&str]) -> Result<Self, String>;
+}
+
+/// Reads the rows of a CTD table; `#` lines, which hold the header and field list, and blank lines are skipped.
+pub struct TsvReader<R: io::BufRead, T: TsvRecord> {
+    input: R,
+    line: String,
+    line_number: usize,
+    done: bool,
+    record: marker::PhantomData<T>,
+}
+
+impl<R: io::BufRead, T: TsvRecord> TsvReader<R, T> {
+    pub fn new(input: R) -> TsvReader<R, T> {
+        TsvReader { input, line: String::new(), line_number: 0, done: false, record: marker::PhantomData }
+    }
+}
+
+/// Yields `Err` for a row that does not parse and carries on with the next one; a read error ends the iteration.
+impl<R: io::BufRead, T: TsvRecord> Iterator for TsvReader<R, T> {
+    type Item = Result<T, CtdError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while !self.done {
+            self.line.clear();
+            self.line_number += 1;
+            match self.input.read_line(&mut self.line) {
+                Ok(0) => self.done = true,
+                Ok(_) if self.line.trim().is_empty() || self.line.starts_with('#') => {}
+                Ok(_) => {
+                    let columns = self.line.trim_end_matches(['\n', '\r']).split('\t').collect::<Vec<_>>();
+                    let record = match columns.len() == T::COLUMNS.len() {
+                        true => T::from_columns(&columns),
+                        false => Err(format!("{} columns, expected {} ({})", columns.len(), T::COLUMNS.len(), T::COLUMNS.join(", "))),
+                    };
+                    return Some(record.map_err(|message| CtdError::Tsv { line: self.line_number, message }));
+                }
+                Err(e) => {
+                    self.done = true;
+                    return Some(Err(CtdError::Io { message: e.to_string() }));
+                }
+            }
+        }
+        None
+    }
+}
+
+/// An empty column as `None`.
+pub fn optional(column: &str) -> Option<String> {
+    match column.trim() {
+        "" => None,
+        value => Some(value.to_string()),
+    }
+}
+
+/// Parses a number column, naming the column on failure.
+pub fn number<T: str::FromStr>(column: &str, name: &str) -> Result<T, String> {
+    optional_number(column, name)?.ok_or_else(|| format!("{} is empty", name))
+}
+
+/// Same as `number`, but an empty column is `None`.
+pub fn optional_number<T: str::FromStr>(column: &str, name: &str) -> Result<Option<T>, String> {
+    match column.trim() {
+        "" => Ok(None),
+        value => value.parse().map(Some).map_err(|_| format!("{} is not a number: {:?}", name, value)),
+    }
+}
+
+/// A CTD id with or without its prefix, e.g. `D000001` or `MESH:D000001`, as the prefixed id.
+pub fn prefixed_id(column: &str, prefix: &str, name: &str) -> Result<String, String> {
+    match column.trim() {
+        "" => Err(format!("{} is empty", name)),
+        id if id.starts_with(prefix) => Ok(id.to_string()),
+        id => Ok(format!("{}{}", prefix, id)),
+    }
+}
+
+/// Splits a `|`-separated list column, e.g. `PubMedIDs`.
+pub fn list(column: &str) -> Vec<String> {
+    column.split('|').map(str::trim).filter(|value| !value.is_empty()).map(String::from).collect()
+}
+
+/// Splits a `|`-separated list of PubMed ids.
+pub fn pm_ids(column: &str) -> Result<Vec<i32>, String> {
+    list(column).iter().map(|pm_id| pm_id.parse().map_err(|_| format!("PubMedIDs has a non-numeric id: {:?}", pm_id))).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, PartialEq)]
+    struct Row {
+        name: String,
+        count: u32,
+        ids: Vec<String>,
+    }
+
+    impl TsvRecord for Row {
+        const COLUMNS: &'static [&'static str] = &["Name", "Count", "IDs"];
+
+        fn from_columns(columns: &[&str]) -> Result<Row, String> {
+            Ok(Row { name: columns[0].to_string(), count: number(columns[1], "Count")?, ids: list(columns[2]) })
+        }
+    }
+
+    fn row(name: &str, count: u32, ids: &[&str]) -> Row {
+        Row { name: name.to_string(), count, ids: ids.iter().map(|id| id.to_string()).collect() }
+    }
+
+    #[test]
+    fn reader_skips_comments_and_blank_lines() {
+        let input = "# CTD table\n#\n# Fields:\n# Name\tCount\tIDs\n#\na\t1\tX|Y\n\n  \nb\t2\t\r\n";
+        let rows = TsvReader::<_, Row>::new(input.as_bytes()).collect::<Result<Vec<_>, _>>().unwrap();
+        assert_eq!(rows, vec![row("a", 1, &["X", "Y"]), row("b", 2, &[])]);
+    }
+
+    #[test]
+    fn reader_reports_bad_rows_by_line_and_carries_on() {
+        let input = "# Fields:\na\t1\n\nb\tmany\tX\nc\t3\tZ\n";
+        let rows = TsvReader::<_, Row>::new(input.as_bytes()).collect::<Vec<_>>();
+        assert_eq!(
+            rows,
+            vec![
+                Err(CtdError::Tsv { line: 2, message: String::from("2 columns, expected 3 (Name, Count, IDs)") }),
+                Err(CtdError::Tsv { line: 4, message: String::from("Count is not a number: \"many\"") }),
+                Ok(row("c", 3, &["Z"])),
+            ]
+        );
+    }
+
+    #[test]
+    fn column_helpers() {
+        assert_eq!((optional(" "), optional(" x ")), (None, Some(String::from("x"))));
+        assert_eq!(number::<f64>("0.5", "PValue"), Ok(0.5));
+        assert_eq!(number::<u32>("", "Qty"), Err(String::from("Qty is empty")));
+        assert_eq!((optional_number::<f64>("", "Score"), optional_number::<f64>("2", "Score")), (Ok(None), Ok(Some(2.0))));
+        assert_eq!(prefixed_id("D000001", "MESH:", "ChemicalID"), Ok(String::from("MESH:D000001")));
+        assert_eq!(prefixed_id("MESH:D000001", "MESH:", "ChemicalID"), Ok(String::from("MESH:D000001")));
+        assert_eq!(prefixed_id(" ", "MESH:", "ChemicalID"), Err(String::from("ChemicalID is empty")));
+        assert_eq!(list(" a | |b"), vec!["a", "b"]);
+        assert_eq!(pm_ids("1|22"), Ok(vec![1, 22]));
+        assert_eq!(pm_ids(""), Ok(Vec::new()));
+        assert_eq!(pm_ids("1|x"), Err(String::from("PubMedIDs has a non-numeric id: \"x\"")));
+    }
+}
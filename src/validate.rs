@@ -0,0 +1,422 @@
+use crate::actor_type::LabelStrategy;
+use crate::config::Config;
+use crate::model::*;
+use quick_xml::events::{BytesStart, Event};
+use std::any;
+use std::collections;
+use std::io;
+use std::str;
+
+/// A place where the input departs from the structure the parser expects.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Violation {
+    pub line: usize,
+    pub ixn_id: Option<i32>,
+    pub message: String,
+}
+
+/// Counts the lines consumed from the wrapped reader, so that every event can be located.
+struct LineCountingReader<R: io::BufRead> {
+    inner: R,
+    lines: usize,
+}
+
+impl<R: io::BufRead> io::Read for LineCountingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.lines += buf[..n].iter().filter(|b| **b == b'\n').count();
+        Ok(n)
+    }
+}
+
+impl<R: io::BufRead> io::BufRead for LineCountingReader<R> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        self.inner.fill_buf()
+    }
+
+    fn consume(&mut self, amt: usize) {
+        if let Ok(buf) = self.inner.fill_buf() {
+            self.lines += buf[..amt].iter().filter(|b| **b == b'\n').count();
+        }
+        self.inner.consume(amt)
+    }
+}
+
+/// An open element and what has been seen inside it so far.
+struct Frame {
+    name: String,
+    line: usize,
+    /// The id that `parentid` of child axns and actors must match: the `<ixn>`'s, or a nested `ixn` actor's.
+    parent_id: Option<i32>,
+    nested_ixn: bool,
+    /// Whether the element must have text, e.g. not for a gene actor labelled by its seqid.
+    text_required: bool,
+    has_text: bool,
+    children: collections::HashMap<String, usize>,
+}
+
+impl Frame {
+    fn count(&self, child: &str) -> usize {
+        self.children.get(child).copied().unwrap_or(0)
+    }
+}
+
+/// Checks structured CTD XML against the layout the parser and converter rely on: the element nesting of `<ixnset>`, `<ixn>`, `<taxon>`, `<reference>`, `<axn>` and `<actor>`, their required attributes, action and degree codes, actor types and `parentid` links.
+pub struct SchemaValidator<'a> {
+    config: &'a Config,
+}
+
+impl<'a> SchemaValidator<'a> {
+    /// Codes and actor types are allowed if `config` knows them.
+    pub fn new(config: &'a Config) -> SchemaValidator<'a> {
+        SchemaValidator { config }
+    }
+
+    /// Streams the input, handing every violation to `report`, and returns the number of `<ixn>` elements checked.
+    ///
+    /// Malformed XML is reported as a violation and ends the check, since nothing after it can be located reliably.
+    pub fn validate<R: io::BufRead, F: FnMut(Violation)>(&self, input: R, mut report: F) -> usize {
+        let mut reader = quick_xml::Reader::from_reader(LineCountingReader { inner: input, lines: 0 });
+        let mut buf = Vec::new();
+        let mut stack: Vec<Frame> = Vec::new();
+        let mut ixn_id = None;
+        let mut ixn_count = 0;
+        loop {
+            buf.clear();
+            let line = reader.get_ref().lines + 1;
+            let event = match reader.read_event_into(&mut buf) {
+                Ok(event) => event,
+                Err(e) => {
+                    report(Violation { line, ixn_id, message: format!("malformed xml: {}", e) });
+                    return ixn_count;
+                }
+            };
+            match event {
+                Event::Start(ref e) | Event::Empty(ref e) => {
+                    let name = String::from_utf8_lossy(e.name().as_ref()).into_owned();
+                    let attributes = match attributes(e) {
+                        Ok(attributes) => attributes,
+                        Err(message) => {
+                            report(Violation { line, ixn_id, message: format!("<{}>: {}", name, message) });
+                            collections::HashMap::new()
+                        }
+                    };
+                    if name == "ixn" {
+                        ixn_count += 1;
+                        ixn_id = attributes.get("id").and_then(|id| id.parse().ok());
+                    }
+                    let mut messages = Vec::new();
+                    let frame = self.open(&name, &attributes, stack.last_mut(), line, &mut messages);
+                    messages.into_iter().for_each(|message| report(Violation { line, ixn_id, message }));
+                    if matches!(event, Event::Start(_)) {
+                        stack.push(frame);
+                    } else {
+                        self.close(&frame).into_iter().for_each(|message| report(Violation { line, ixn_id, message }));
+                        if frame.name == "ixn" {
+                            ixn_id = None;
+                        }
+                    }
+                }
+                Event::Text(ref t) if !t.iter().all(u8::is_ascii_whitespace) => {
+                    if let Some(frame) = stack.last_mut() {
+                        frame.has_text = true;
+                    }
+                }
+                Event::CData(_) => {
+                    if let Some(frame) = stack.last_mut() {
+                        frame.has_text = true;
+                    }
+                }
+                Event::End(_) => {
+                    if let Some(frame) = stack.pop() {
+                        self.close(&frame).into_iter().for_each(|message| report(Violation { line: frame.line, ixn_id, message }));
+                        if frame.name == "ixn" {
+                            ixn_id = None;
+                        }
+                    }
+                }
+                Event::Eof => {
+                    if let Some(frame) = stack.last() {
+                        report(Violation { line, ixn_id, message: format!("input ends inside <{}> opened on line {}", frame.name, frame.line) });
+                    } else if ixn_count == 0 {
+                        report(Violation { line, ixn_id: None, message: String::from("no <ixn> elements") });
+                    }
+                    return ixn_count;
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Checks an element's placement and attributes, returning the frame for its content.
+    fn open(&self, name: &str, attributes: &collections::HashMap<String, String>, parent: Option<&mut Frame>, line: usize, messages: &mut Vec<String>) -> Frame {
+        let mut frame = Frame {
+            name: name.to_string(),
+            line,
+            parent_id: None,
+            nested_ixn: false,
+            text_required: ["taxon", "axn", "actor"].contains(&name),
+            has_text: false,
+            children: collections::HashMap::new(),
+        };
+        let enclosing_parent_id = parent.as_ref().and_then(|p| p.parent_id);
+        let allowed = match parent.as_ref() {
+            None => name == "ixnset",
+            Some(p) => match p.name.as_str() {
+                "ixnset" => name == "ixn",
+                "ixn" => ["taxon", "reference", "axn", "actor"].contains(&name),
+                "actor" => p.nested_ixn && ["axn", "actor"].contains(&name),
+                _ => false,
+            },
+        };
+        if !allowed {
+            match parent.as_ref() {
+                Some(p) => messages.push(format!("<{}> is not allowed inside <{}>{}", name, p.name, if p.name == "actor" { " unless it has type \"ixn\"" } else { "" })),
+                None => messages.push(format!("root element is <{}>, expected <ixnset>", name)),
+            }
+        }
+        if let Some(p) = parent {
+            *p.children.entry(name.to_string()).or_default() += 1;
+        }
+        let mut integers = Vec::new();
+        match name {
+            "ixn" => {
+                frame.parent_id = required(name, attributes, "id", messages).and_then(|id| integer(name, "id", id, messages));
+            }
+            "taxon" => integers.push("id"),
+            "reference" => integers.push("pmid"),
+            "axn" => {
+                if let Some(code) = required(name, attributes, "code", messages) {
+                    if code != "rxn" && !self.config.class_map.contains_key(code) {
+                        messages.push(format!("<axn> has unknown code {:?}", code));
+                    }
+                }
+                if let Some(degree_code) = required(name, attributes, "degreecode", messages) {
+                    let mut chars = degree_code.chars();
+                    match (chars.next(), chars.next()) {
+                        (Some(c), None) if !matches!(Degree::from_code(c), Degree::Unknown(_)) => {}
+                        _ => messages.push(format!("<axn> has unknown degreecode {:?}", degree_code)),
+                    }
+                }
+                integers.extend(["position", "parentid"]);
+            }
+            "actor" => {
+                let id = required(name, attributes, "id", messages);
+                if let Some(actor_type) = required(name, attributes, "type", messages) {
+                    if actor_type == "ixn" {
+                        frame.nested_ixn = true;
+                        frame.parent_id = id.and_then(|id| integer(name, "id", id, messages));
+                        frame.text_required = false;
+                    } else {
+                        match self.config.actor_types.get(actor_type) {
+                            Some(registered) => frame.text_required = registered.label == LabelStrategy::Text || !attributes.contains_key("seqid"),
+                            None => {
+                                frame.text_required = false;
+                                messages.push(format!("<actor> has unknown type {:?}", actor_type));
+                            }
+                        }
+                    }
+                }
+                integers.extend(["position", "parentid"]);
+            }
+            _ => {}
+        }
+        for attribute in integers {
+            let value = required(name, attributes, attribute, messages);
+            // positions are kept as i8 by the model, so anything past 127 would only fail in parse
+            if let Some(position) = value.filter(|_| attribute == "position") {
+                integer::<i8>(name, attribute, position, messages);
+                continue;
+            }
+            let value = value.and_then(|value| integer::<i32>(name, attribute, value, messages));
+            if let (Some(parent_id), Some(enclosing)) = (value.filter(|_| attribute == "parentid"), enclosing_parent_id) {
+                if parent_id != enclosing {
+                    messages.push(format!("<{}> has parentid {} inside interaction {}", name, parent_id, enclosing));
+                }
+            }
+        }
+        frame
+    }
+
+    /// Checks what an element contained once it is closed.
+    fn close(&self, frame: &Frame) -> Vec<String> {
+        let mut messages = Vec::new();
+        let mut at_least = |child: &str, n: usize, what: &str| {
+            if frame.count(child) < n {
+                messages.push(format!("{} has {} <{}>, expected at least {}", what, frame.count(child), child, n));
+            }
+        };
+        match frame.name.as_str() {
+            "ixn" => {
+                at_least("taxon", 1, "<ixn>");
+                at_least("reference", 1, "<ixn>");
+                at_least("axn", 1, "<ixn>");
+                at_least("actor", 2, "<ixn>");
+            }
+            "actor" if frame.nested_ixn => {
+                at_least("axn", 1, "nested <actor type=\"ixn\">");
+                at_least("actor", 2, "nested <actor type=\"ixn\">");
+                if frame.has_text {
+                    messages.push(String::from("nested <actor type=\"ixn\"> has text"));
+                }
+            }
+            _ => {}
+        }
+        if frame.text_required && !frame.has_text {
+            messages.push(format!("<{}> has no text", frame.name));
+        }
+        messages
+    }
+}
+
+fn attributes(start: &BytesStart) -> Result<collections::HashMap<String, String>, String> {
+    let mut attributes = collections::HashMap::new();
+    for attribute in start.attributes() {
+        let attribute = attribute.map_err(|e| e.to_string())?;
+        let key = String::from_utf8_lossy(attribute.key.as_ref()).into_owned();
+        let value = attribute.unescape_value().map_err(|e| e.to_string())?.into_owned();
+        attributes.insert(key, value);
+    }
+    Ok(attributes)
+}
+
+fn required<'m>(name: &str, attributes: &'m collections::HashMap<String, String>, attribute: &str, messages: &mut Vec<String>) -> Option<&'m String> {
+    let value = attributes.get(attribute);
+    if value.is_none() {
+        messages.push(format!("<{}> is missing attribute '{}'", name, attribute));
+    }
+    value
+}
+
+fn integer<T: str::FromStr>(name: &str, attribute: &str, value: &str, messages: &mut Vec<String>) -> Option<T> {
+    match value.parse() {
+        Ok(i) => Some(i),
+        Err(_) => {
+            messages.push(format!("<{}> attribute '{}' is not a valid {} integer: {:?}", name, attribute, any::type_name::<T>(), value));
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const IXN: &str = r#"<ixn id="1">
+  <taxon id="9606">Homo sapiens</taxon>
+  <reference pmid="10"/>
+  <axn code="exp" degreecode="+" position="1" parentid="1">increases expression</axn>
+  <actor type="chemical" id="MESH:D000001" position="1" parentid="1">A</actor>
+  <actor type="gene" id="GENE:1" position="2" parentid="1">G1</actor>
+</ixn>"#;
+
+    /// The violations in `<ixnset>` around `ixns`, as line, ixn id and message.
+    fn violations(ixns: &str) -> Vec<(usize, Option<i32>, String)> {
+        let config = Config::default();
+        let mut violations = Vec::new();
+        SchemaValidator::new(&config).validate(format!("<ixnset>\n{}\n</ixnset>\n", ixns).as_bytes(), |v| violations.push((v.line, v.ixn_id, v.message)));
+        violations
+    }
+
+    fn messages(ixns: &str) -> Vec<String> {
+        violations(ixns).into_iter().map(|(_, _, message)| message).collect()
+    }
+
+    /// `IXN` with `from` replaced by `to`.
+    fn with(from: &str, to: &str) -> String {
+        assert!(IXN.contains(from), "{:?}", from);
+        IXN.replacen(from, to, 1)
+    }
+
+    #[test]
+    fn a_well_formed_interaction_has_no_violations() {
+        let config = Config::default();
+        let mut violations = Vec::new();
+        let count = SchemaValidator::new(&config).validate(format!("<ixnset>\n{}\n{}\n</ixnset>\n", IXN, IXN.replace("\"1\"", "\"2\"")).as_bytes(), |v| violations.push(v));
+        assert_eq!((count, violations), (2, Vec::new()));
+    }
+
+    #[test]
+    fn nesting_is_checked() {
+        assert_eq!(messages(&with("<reference pmid=\"10\"/>", "<reference pmid=\"10\"><taxon id=\"1\">t</taxon></reference>")), vec!["<taxon> is not allowed inside <reference>"]);
+        assert_eq!(
+            messages(&with("A</actor>", "A<axn code=\"exp\" degreecode=\"+\" position=\"1\" parentid=\"1\">t</axn></actor>")),
+            vec!["<axn> is not allowed inside <actor> unless it has type \"ixn\""]
+        );
+        assert_eq!(messages(&with("<reference pmid=\"10\"/>\n", "")), vec!["<ixn> has 0 <reference>, expected at least 1"]);
+        let config = Config::default();
+        let mut violations = Vec::new();
+        SchemaValidator::new(&config).validate(IXN.as_bytes(), |v| violations.push(v.message));
+        assert_eq!(violations, vec!["root element is <ixn>, expected <ixnset>"]);
+    }
+
+    #[test]
+    fn nested_interactions_need_axns_and_two_actors_and_no_text() {
+        let nested = with(
+            "<actor type=\"gene\" id=\"GENE:1\" position=\"2\" parentid=\"1\">G1</actor>",
+            "<actor type=\"ixn\" id=\"2\" position=\"2\" parentid=\"1\">text<actor type=\"gene\" id=\"GENE:1\" position=\"1\" parentid=\"2\">G1</actor></actor>",
+        );
+        assert_eq!(
+            messages(&nested),
+            vec![
+                "nested <actor type=\"ixn\"> has 0 <axn>, expected at least 1",
+                "nested <actor type=\"ixn\"> has 1 <actor>, expected at least 2",
+                "nested <actor type=\"ixn\"> has text"
+            ]
+        );
+    }
+
+    #[test]
+    fn required_attributes_and_text_are_checked() {
+        assert_eq!(messages(&with(" degreecode=\"+\"", "")), vec!["<axn> is missing attribute 'degreecode'"]);
+        assert_eq!(messages(&with(" type=\"chemical\"", "")), vec!["<actor> is missing attribute 'type'"]);
+        assert_eq!(messages(&with("<ixn id=\"1\">", "<ixn>")), vec!["<ixn> is missing attribute 'id'"]);
+        assert_eq!(messages(&with("Homo sapiens", "")), vec!["<taxon> has no text"]);
+        // a gene may be labelled by its seqid instead
+        assert_eq!(messages(&with(" parentid=\"1\">G1", " parentid=\"1\" seqid=\"NM_1\">")), Vec::<String>::new());
+        assert_eq!(messages(&with(" parentid=\"1\">A", " parentid=\"1\" seqid=\"NM_1\">")), vec!["<actor> has no text"]);
+    }
+
+    #[test]
+    fn codes_degrees_and_actor_types_must_be_known() {
+        assert_eq!(messages(&with("code=\"exp\"", "code=\"xyz\"")), vec!["<axn> has unknown code \"xyz\""]);
+        assert_eq!(messages(&with("degreecode=\"+\"", "degreecode=\"?\"")), vec!["<axn> has unknown degreecode \"?\""]);
+        assert_eq!(messages(&with("degreecode=\"+\"", "degreecode=\"+-\"")), vec!["<axn> has unknown degreecode \"+-\""]);
+        assert_eq!(messages(&with("type=\"chemical\"", "type=\"protein\"")), vec!["<actor> has unknown type \"protein\""]);
+    }
+
+    #[test]
+    fn integers_and_parent_ids_are_checked() {
+        assert_eq!(messages(&with("pmid=\"10\"", "pmid=\"1x\"")), vec!["<reference> attribute 'pmid' is not a valid i32 integer: \"1x\""]);
+        // positions have to fit the model's i8
+        assert_eq!(messages(&with("position=\"2\"", "position=\"128\"")), vec!["<actor> attribute 'position' is not a valid i8 integer: \"128\""]);
+        assert_eq!(messages(&with("position=\"1\" parentid=\"1\">A", "position=\"1\" parentid=\"3\">A")), vec!["<actor> has parentid 3 inside interaction 1"]);
+    }
+
+    #[test]
+    fn violations_carry_their_line_and_interaction() {
+        let ixns = format!("{}\n{}", IXN, with("code=\"exp\"", "code=\"xyz\"").replace("\"1\"", "\"2\""));
+        // <ixnset> is line 1 and each interaction takes 7 lines; an unclosed ixn is reported where it was opened
+        assert_eq!(violations(&ixns), vec![(12, Some(2), String::from("<axn> has unknown code \"xyz\""))]);
+        assert_eq!(violations(&with("<reference pmid=\"10\"/>\n", "")), vec![(2, Some(1), String::from("<ixn> has 0 <reference>, expected at least 1"))]);
+    }
+
+    #[test]
+    fn a_self_closing_ixn_ends_its_interaction() {
+        let found = violations("<ixn id=\"7\"/>\n<bad/>");
+        assert_eq!(found[0], (2, Some(7), String::from("<ixn> has 0 <taxon>, expected at least 1")));
+        assert_eq!(found.last().unwrap(), &(3, None, String::from("<bad> is not allowed inside <ixnset>")));
+    }
+
+    #[test]
+    fn malformed_xml_ends_the_check() {
+        let config = Config::default();
+        let mut violations = Vec::new();
+        let count = SchemaValidator::new(&config)
+            .validate(format!("<ixnset>\n{}\n<ixn id=\"2\"><taxon id=\"1\">t</reference>\n</ixn>\n</ixnset>\n", IXN).as_bytes(), |v| violations.push(v));
+        assert_eq!(count, 2);
+        assert_eq!(violations.len(), 1);
+        assert_eq!((violations[0].line, violations[0].ixn_id), (9, Some(2)));
+        assert!(violations[0].message.starts_with("malformed xml"), "{}", violations[0].message);
+    }
+}
@@ -0,0 +1,339 @@
+use crate::config::Config;
+use crate::converter;
+use crate::model::*;
+use crate::tsv;
+use horned_owl::model::*;
+use horned_owl::vocab::WithIRI;
+use std::collections;
+use std::error;
+use std::path;
+
+/// How many malformed rows are logged individually when a vocabulary is loaded.
+const EXAMPLE_LIMIT: usize = 5;
+
+/// A row of a CTD vocabulary table, describing the class of one actor id.
+pub trait VocabularyTerm: tsv::TsvRecord {
+    /// The actor type whose ids the vocabulary describes, e.g. `chemical`.
+    const ACTOR_TYPE: &'static str;
+
+    /// The id as actors have it, e.g. `MESH:D000001`.
+    fn id(&self) -> &str;
+
+    fn label(&self) -> &str;
+
+    /// Ids of the broader terms, each made a superclass.
+    fn parent_ids(&self) -> &[String];
+
+    /// Annotations besides the label, as `(property, property label, value)`; `config` names the CTD-specific properties.
+    fn annotations(&self, config: &Config) -> Vec<(String, &'static str, String)>;
+}
+
+impl VocabularyTerm for ChemicalTerm {
+    const ACTOR_TYPE: &'static str = "chemical";
+
+    fn id(&self) -> &str {
+        &self.id
+    }
+
+    fn label(&self) -> &str {
+        &self.name
+    }
+
+    fn parent_ids(&self) -> &[String] {
+        &self.parent_ids
+    }
+
+    fn annotations(&self, config: &Config) -> Vec<(String, &'static str, String)> {
+        let mut annotations = Vec::new();
+        if let Some(definition) = self.definition.as_ref() {
+            annotations.push((crate::DEFINITION.to_string(), "definition", definition.clone()));
+        }
+        for synonym in self.synonyms.iter() {
+            annotations.push((crate::HAS_EXACT_SYNONYM.to_string(), "has_exact_synonym", synonym.clone()));
+        }
+        if let Some(cas_rn) = self.cas_rn.as_ref() {
+            annotations.push((crate::HAS_DB_XREF.to_string(), "database_cross_reference", format!("CAS:{}", cas_rn)));
+        }
+        for tree_number in self.tree_numbers.iter() {
+            annotations.push((config.ctd_term("tree_number"), "tree number", tree_number.clone()));
+        }
+        annotations
+    }
+}
+
+impl VocabularyTerm for GeneTerm {
+    const ACTOR_TYPE: &'static str = "gene";
+
+    fn id(&self) -> &str {
+        &self.id
+    }
+
+    fn label(&self) -> &str {
+        &self.symbol
+    }
+
+    /// Genes have no hierarchy.
+    fn parent_ids(&self) -> &[String] {
+        &[]
+    }
+
+    fn annotations(&self, _config: &Config) -> Vec<(String, &'static str, String)> {
+        let mut annotations = Vec::new();
+        if !self.name.is_empty() {
+            annotations.push((crate::HAS_EXACT_SYNONYM.to_string(), "has_exact_synonym", self.name.clone()));
+        }
+        for synonym in self.synonyms.iter() {
+            annotations.push((crate::HAS_RELATED_SYNONYM.to_string(), "has_related_synonym", synonym.clone()));
+        }
+        for uniprot_id in self.uniprot_ids.iter() {
+            annotations.push((crate::HAS_DB_XREF.to_string(), "database_cross_reference", format!("UniProtKB:{}", uniprot_id)));
+        }
+        for biogrid_id in self.biogrid_ids.iter() {
+            annotations.push((crate::HAS_DB_XREF.to_string(), "database_cross_reference", format!("BioGRID:{}", biogrid_id)));
+        }
+        annotations
+    }
+}
+
+/// A CTD vocabulary keyed by actor id, used in place of the text of each interaction to label and place the classes it converts.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Vocabulary<T: VocabularyTerm> {
+    terms: collections::HashMap<String, T>,
+}
+
+impl<T: VocabularyTerm> Vocabulary<T> {
+    /// Reads a CTD vocabulary table, which may be gzip-compressed; rows that do not parse are logged and skipped.
+    pub fn load(vocabulary_path: &path::Path) -> Result<Vocabulary<T>, Box<dyn error::Error>> {
+        let mut terms = collections::HashMap::new();
+        let mut malformed = 0;
+        for result in tsv::TsvReader::<_, T>::new(crate::parser::open_input(vocabulary_path)?) {
+            match result {
+                Ok(term) => {
+                    terms.insert(term.id().to_string(), term);
+                }
+                Err(e) if e.is_recoverable() => {
+                    if malformed < EXAMPLE_LIMIT {
+                        warn!("{:?}: skipping {}", vocabulary_path, e);
+                    }
+                    malformed += 1;
+                }
+                Err(e) => return Err(e.into()),
+            }
+        }
+        info!("{:?}: {} {} terms", vocabulary_path, terms.len(), T::ACTOR_TYPE);
+        if malformed > 0 {
+            warn!("{:?}: skipped {} malformed rows", vocabulary_path, malformed);
+        }
+        Ok(Vocabulary { terms })
+    }
+
+    pub fn get(&self, id: &str) -> Option<&T> {
+        self.terms.get(id)
+    }
+
+    /// Labels, annotations and superclasses of the classes of `ids` and of every ancestor they have in the vocabulary.
+    ///
+    /// `class_iri` resolves an id to the class interactions use for it, e.g. the CHEBI class of a mapped chemical, so the hierarchy is stated on those classes.
+    pub fn axioms<'i, I: IntoIterator<Item = &'i str>, F: Fn(&str) -> String>(&self, build: &Build, config: &Config, class_iri: F, ids: I) -> Vec<Axiom> {
+        let mut axioms: Vec<Axiom> = Vec::new();
+        let mut seen = collections::HashSet::new();
+        let mut missing = 0;
+        let mut queue = ids.into_iter().map(String::from).collect::<Vec<_>>();
+        while let Some(id) = queue.pop() {
+            if !seen.insert(id.clone()) {
+                continue;
+            }
+            let term = match self.terms.get(&id) {
+                Some(term) => term,
+                None => {
+                    debug!("not in the {} vocabulary: {}", T::ACTOR_TYPE, id);
+                    missing += 1;
+                    continue;
+                }
+            };
+            let class = build.class(class_iri(&id));
+            axioms.push(Axiom::DeclareClass(DeclareClass(class.clone())));
+            axioms.push(Axiom::AnnotationAssertion(AnnotationAssertion::new(
+                class.0.clone(),
+                Annotation {
+                    ap: build.annotation_property(horned_owl::vocab::RDFS::Label.iri_s()),
+                    av: AnnotationValue::Literal(Literal::Simple { literal: term.label().to_string() }),
+                },
+            )));
+            for (property, property_label, value) in term.annotations(config) {
+                axioms.append(&mut converter::build_annotation_property(build, &property, property_label));
+                axioms.push(Axiom::AnnotationAssertion(AnnotationAssertion::new(
+                    class.0.clone(),
+                    Annotation { ap: build.annotation_property(property), av: AnnotationValue::Literal(Literal::Simple { literal: value }) },
+                )));
+            }
+            // class SubClassOf parentClass, unless both resolve to the same class
+            for parent_id in term.parent_ids() {
+                queue.push(parent_id.clone());
+                let parent_class = build.class(class_iri(parent_id));
+                if parent_class == class {
+                    continue;
+                }
+                axioms.push(Axiom::DeclareClass(DeclareClass(parent_class.clone())));
+                axioms.push(Axiom::SubClassOf(SubClassOf { sub: class.clone().into(), sup: parent_class.into() }));
+            }
+        }
+        if missing > 0 {
+            warn!("{} {} ids, referenced or given as a parent, are not in the vocabulary", missing, T::ACTOR_TYPE);
+        }
+        axioms
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::converter::Converter;
+    use crate::mapping::ChebiMeshMapping;
+    use itertools::Itertools;
+    use std::fs;
+
+    const CHEMICALS: &str = "# ChemicalName\tChemicalID\tCasRN\tDefinition\tParentIDs\tTreeNumbers\tParentTreeNumbers\tSynonyms\n\
+        Water\tMESH:D014867\t7732-18-5\tH2O\tD000002\tD01.045|D01.650\tD01\tDihydrogen oxide|Ice\n\
+        Inorganic Chemicals\tMESH:D000002\t\t\tD000001\tD01\t\t\n\
+        Root\tMESH:D000001\t\t\t\t\t\t\n\
+        Unnamed\t\t\t\t\t\t\t\n";
+
+    const GENES: &str = "# GeneSymbol\tGeneName\tGeneID\tAltGeneIDs\tSynonyms\tBioGRIDIDs\tPharmGKBIDs\tUniProtIDs\n\
+        CYP1A1\tcytochrome P450 family 1 subfamily A member 1\t1543\t\tCP11|CYP1\t107935\tPA198\tP04798|A0N0X8\n";
+
+    /// Loads `tsv` as a vocabulary table of its own under the system temp directory.
+    fn load<T: VocabularyTerm>(name: &str, tsv: &str) -> Vocabulary<T> {
+        let path = std::env::temp_dir().join(format!("ctd-to-owl-{}-{}.tsv", std::process::id(), name));
+        fs::write(&path, tsv).unwrap();
+        let vocabulary = Vocabulary::load(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        vocabulary
+    }
+
+    /// A converter mapping each MESH id in `mapping` to its CHEBI id.
+    fn converter(mapping: &[(&str, &str)]) -> Converter {
+        Converter::new(ChebiMeshMapping::from(mapping.iter().map(|(mesh, chebi)| (mesh.to_string(), chebi.to_string())).collect::<collections::HashMap<_, _>>()))
+    }
+
+    /// Axioms for `ids` with classes resolved as `converter` resolves actors of `actor_type`.
+    fn axioms<T: VocabularyTerm>(converter: &Converter, vocabulary: &Vocabulary<T>, actor_type: &str, ids: &[&str]) -> Vec<Axiom> {
+        let registered = converter.config().actor_types.get(actor_type).unwrap();
+        vocabulary.axioms(&Build::new(), converter.config(), |id| converter.resolve_id(registered, id, String::new()).class_iri, ids.iter().copied())
+    }
+
+    /// The annotation assertions on `class_iri` as property IRI and value, sorted.
+    fn annotations(axioms: &[Axiom], class_iri: &str) -> Vec<(String, String)> {
+        let mut annotations = axioms
+            .iter()
+            .filter_map(|axiom| match axiom {
+                Axiom::AnnotationAssertion(AnnotationAssertion { subject, ann: Annotation { ap, av: AnnotationValue::Literal(Literal::Simple { literal }) } })
+                    if subject.to_string() == class_iri =>
+                {
+                    Some((ap.0.to_string(), literal.clone()))
+                }
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        annotations.sort();
+        annotations
+    }
+
+    /// The `SubClassOf` axioms between named classes as sub and super IRI, sorted.
+    fn subclasses(axioms: &[Axiom]) -> Vec<(String, String)> {
+        let mut subclasses = axioms
+            .iter()
+            .filter_map(|axiom| match axiom {
+                Axiom::SubClassOf(SubClassOf { sub: ClassExpression::Class(sub), sup: ClassExpression::Class(sup) }) => Some((sub.0.to_string(), sup.0.to_string())),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        subclasses.sort();
+        subclasses
+    }
+
+    fn annotation(property: &str, value: &str) -> (String, String) {
+        (property.to_string(), value.to_string())
+    }
+
+    #[test]
+    fn load_keys_terms_by_prefixed_id_and_skips_malformed_rows() {
+        let chemicals = load::<ChemicalTerm>("chemicals", CHEMICALS);
+        assert_eq!(chemicals.get("MESH:D014867").map(VocabularyTerm::label), Some("Water"));
+        assert_eq!(chemicals.get("MESH:D000002").map(VocabularyTerm::parent_ids), Some(&[String::from("MESH:D000001")][..]));
+        assert_eq!(chemicals.get("D014867"), None);
+        assert_eq!(chemicals.terms.len(), 3);
+
+        let genes = load::<GeneTerm>("genes", GENES);
+        assert_eq!(genes.get("GENE:1543").map(VocabularyTerm::label), Some("CYP1A1"));
+    }
+
+    #[test]
+    fn chemicals_get_label_synonyms_xrefs_and_tree_numbers() {
+        let axioms = axioms(&converter(&[]), &load::<ChemicalTerm>("chemical-annotations", CHEMICALS), "chemical", &["MESH:D014867"]);
+        let tree_number = Config::default().ctd_term("tree_number");
+        assert_eq!(
+            annotations(&axioms, &format!("{}D014867", crate::MESH)),
+            vec![
+                annotation(horned_owl::vocab::RDFS::Label.iri_s(), "Water"),
+                annotation(&tree_number, "D01.045"),
+                annotation(&tree_number, "D01.650"),
+                annotation(crate::DEFINITION.as_str(), "H2O"),
+                annotation(crate::HAS_DB_XREF.as_str(), "CAS:7732-18-5"),
+                annotation(crate::HAS_EXACT_SYNONYM.as_str(), "Dihydrogen oxide"),
+                annotation(crate::HAS_EXACT_SYNONYM.as_str(), "Ice"),
+            ]
+            .into_iter()
+            .sorted()
+            .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn parents_are_resolved_through_the_chebi_mapping() {
+        let axioms = axioms(&converter(&[("MESH:D000001", "CHEBI:1")]), &load::<ChemicalTerm>("chemical-parents", CHEMICALS), "chemical", &["MESH:D014867"]);
+        // the ancestors are described too, the mapped root on its CHEBI class
+        assert_eq!(
+            subclasses(&axioms),
+            vec![(format!("{}D000002", crate::MESH), format!("{}1", crate::CHEBI)), (format!("{}D014867", crate::MESH), format!("{}D000002", crate::MESH)),]
+        );
+        assert_eq!(annotations(&axioms, &format!("{}1", crate::CHEBI)), vec![annotation(horned_owl::vocab::RDFS::Label.iri_s(), "Root")]);
+        assert!(annotations(&axioms, &format!("{}D000001", crate::MESH)).is_empty());
+    }
+
+    #[test]
+    fn parent_resolving_to_the_same_class_is_not_made_a_superclass() {
+        let tsv = "Water\tMESH:D014867\t\t\tD000001\t\t\t\nRoot\tMESH:D000001\t\t\t\t\t\t\n";
+        let converter = converter(&[("MESH:D014867", "CHEBI:15377"), ("MESH:D000001", "CHEBI:15377")]);
+        let axioms = axioms(&converter, &load::<ChemicalTerm>("chemical-same-class", tsv), "chemical", &["MESH:D014867"]);
+        assert!(subclasses(&axioms).is_empty());
+    }
+
+    #[test]
+    fn genes_are_described_on_their_ncbigene_class() {
+        let axioms = axioms(&converter(&[]), &load::<GeneTerm>("gene-annotations", GENES), "gene", &["GENE:1543"]);
+        assert_eq!(
+            annotations(&axioms, &format!("{}1543", crate::NCBIGENE)),
+            vec![
+                annotation(horned_owl::vocab::RDFS::Label.iri_s(), "CYP1A1"),
+                annotation(crate::HAS_DB_XREF.as_str(), "BioGRID:107935"),
+                annotation(crate::HAS_DB_XREF.as_str(), "UniProtKB:A0N0X8"),
+                annotation(crate::HAS_DB_XREF.as_str(), "UniProtKB:P04798"),
+                annotation(crate::HAS_EXACT_SYNONYM.as_str(), "cytochrome P450 family 1 subfamily A member 1"),
+                annotation(crate::HAS_RELATED_SYNONYM.as_str(), "CP11"),
+                annotation(crate::HAS_RELATED_SYNONYM.as_str(), "CYP1"),
+            ]
+            .into_iter()
+            .sorted()
+            .collect::<Vec<_>>()
+        );
+        assert!(annotations(&axioms, "GENE:1543").is_empty());
+        assert!(subclasses(&axioms).is_empty());
+    }
+
+    #[test]
+    fn ids_missing_from_the_vocabulary_get_no_axioms() {
+        let axioms = axioms(&converter(&[]), &load::<GeneTerm>("gene-missing", GENES), "gene", &["GENE:9999"]);
+        assert!(axioms.is_empty());
+    }
+}